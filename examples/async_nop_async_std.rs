@@ -8,7 +8,7 @@ use liburing_rs::ops::Nop;
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     async_std::task::block_on(async {
         println!("Creating async io_uring with 8 entries...");
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         println!("Submitting NOP operation...");
         let result = ring.submit_op(Nop).await?;
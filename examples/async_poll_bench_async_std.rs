@@ -43,7 +43,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         println!("Creating async io_uring with batch size {}...", BATCH_SIZE);
 
-        let mut ring = AsyncIoUring::new(256)?;
+        let ring = AsyncIoUring::new(256)?;
 
         println!(
             "\nRunning async benchmark for {} seconds...",
@@ -64,14 +64,11 @@ fn copy_file(infd: RawFd, outfd: RawFd, file_size: u64, ring: &mut IoUring) -> R
         }
 
         // Process completions
-        let completions: Vec<(i32, u64)> = {
-            let mut cq = ring.completion();
-            let mut results = Vec::new();
-            while let Some(cqe) = cq.peek_cqe() {
-                results.push((cqe.result(), cqe.user_data()));
-            }
-            results
-        };
+        let completions: Vec<(i32, u64)> = ring
+            .completion()
+            .drain()
+            .map(|c| (c.result(), c.user_data()))
+            .collect();
 
         for (result, user_data) in completions {
             if result < 0 {
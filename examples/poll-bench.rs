@@ -51,11 +51,7 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     // Register the pipe file descriptors as fixed files
     let fds = [read_fd, write_fd];
     unsafe {
-        let ret = liburing_rs::sys::io_uring_register_files(
-            &mut ring as *mut _ as *mut liburing_rs::sys::io_uring,
-            fds.as_ptr(),
-            2,
-        );
+        let ret = liburing_rs::sys::io_uring_register_files(ring.as_raw_mut(), fds.as_ptr(), 2);
         if ret < 0 {
             eprintln!("Warning: io_uring_register_files failed: {}", -ret);
         } else {
@@ -65,9 +61,7 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Register the ring fd
     unsafe {
-        let ret = liburing_rs::sys::io_uring_register_ring_fd(
-            &mut ring as *mut _ as *mut liburing_rs::sys::io_uring,
-        );
+        let ret = liburing_rs::sys::io_uring_register_ring_fd(ring.as_raw_mut());
         if ret < 0 {
             eprintln!("Warning: io_uring_register_ring_fd failed: {}", -ret);
         } else {
@@ -1,10 +1,44 @@
 //! Async I/O support for io_uring
 //!
 //! This module provides async/await interfaces for io_uring operations.
-//! Enable with the `async-tokio` or `async-async-std` features.
+//! Each backend has its own `AsyncIoUring`:
 //!
-//! **Note**: Only one async runtime feature should be enabled at a time.
-//! If both are enabled, tokio will be used by default.
+//! * [`tokio_impl`] (feature `async-tokio`) and [`async_std_impl`]
+//!   (feature `async-async-std`) spawn their reactor on the runtime.
+//! * [`smol_impl`] (feature `async-smol`) waits on the ring through
+//!   `async-io` and hands out its reactor for any executor to run.
+//! * [`generic_impl`] (feature `async`) runs on nothing at all; it makes
+//!   progress whenever its owner calls `drive` or `poll_completions`.
+//! * [`local_impl`] (feature `async`) is driven the same way, but stays on
+//!   one thread: no lock, and the kernel defers its task work until the
+//!   ring is reaped.
+//!
+//! Besides submitting raw operations through `AsyncIoUring`, the [`fs`]
+//! and [`net`] modules offer files and sockets built on the same ring.
+//! They can be opened on the ring of any backend, see [`Ring`]. Timers in
+//! [`time`] are timeouts on the same ring.
+//! Multishot operations are consumed as a [`Multishot`] stream of CQEs.
+//!
+//! **Note**: The `AsyncIoUring` at the root of this module is the first of
+//! tokio's, async-std's, smol's and the generic one whose feature is
+//! enabled. With more than one runtime feature enabled, name the backend
+//! explicitly, as in `async_std_impl::AsyncIoUring`.
+
+mod buf;
+mod driver;
+pub mod fs;
+mod multishot;
+pub mod net;
+pub mod time;
+
+pub use multishot::{LocalMultishot, Multishot};
+
+use driver::{Core, Driver, DriverRef, SharedCore};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub mod generic_impl;
+pub mod local_impl;
 
 #[cfg(feature = "async-tokio")]
 pub mod tokio_impl;
@@ -12,10 +46,76 @@ pub mod tokio_impl;
 #[cfg(feature = "async-async-std")]
 pub mod async_std_impl;
 
+#[cfg(feature = "async-smol")]
+pub mod smol_impl;
+
 // Re-export AsyncIoUring from the appropriate runtime implementation
-// If both features are enabled, prefer tokio
+// If several features are enabled, prefer tokio, then async-std, then smol
 #[cfg(feature = "async-tokio")]
 pub use tokio_impl::AsyncIoUring;
 
 #[cfg(all(feature = "async-async-std", not(feature = "async-tokio")))]
 pub use async_std_impl::AsyncIoUring;
+
+#[cfg(all(
+    feature = "async-smol",
+    not(any(feature = "async-tokio", feature = "async-async-std"))
+))]
+pub use smol_impl::AsyncIoUring;
+
+#[cfg(not(any(
+    feature = "async-tokio",
+    feature = "async-async-std",
+    feature = "async-smol"
+)))]
+pub use generic_impl::AsyncIoUring;
+
+/// A ring that files and sockets can be opened on
+///
+/// Implemented by the `AsyncIoUring` of every backend, so the types in
+/// [`fs`], [`net`] and [`time`] work the same whichever runtime drives the
+/// ring. They are generic over the handle they keep: a [`SharedHandle`]
+/// for the thread-safe backends, a [`LocalHandle`] for [`local_impl`],
+/// whose files and sockets are then neither `Send` nor `Sync`. This trait
+/// is sealed.
+pub trait Ring: sealed::Sealed {}
+
+/// Handle to a thread-safe ring, kept by the I/O types opened on it
+#[derive(Clone)]
+pub struct SharedHandle(pub(crate) driver::Handle);
+
+/// Handle to a [`local_impl`] ring, kept by the I/O types opened on it
+#[derive(Clone)]
+pub struct LocalHandle(pub(crate) Rc<driver::LocalDriver>);
+
+impl DriverRef for SharedHandle {
+    type Cell = SharedCore;
+
+    fn driver(&self) -> &Driver {
+        self.0.driver()
+    }
+}
+
+impl DriverRef for LocalHandle {
+    type Cell = RefCell<Core>;
+
+    fn driver(&self) -> &driver::LocalDriver {
+        &self.0
+    }
+}
+
+/// The handle of a backend's ring
+fn handle<R: Ring>(ring: &R) -> R::Handle {
+    ring.ring_handle()
+}
+
+mod sealed {
+    use super::driver::DriverRef;
+
+    pub trait Sealed {
+        /// What the I/O types opened on the ring hold on to
+        type Handle: DriverRef;
+
+        fn ring_handle(&self) -> Self::Handle;
+    }
+}
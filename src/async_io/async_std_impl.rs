@@ -1,16 +1,21 @@
 //! async-std runtime integration for io_uring
 
-use crate::{
-    ops::{PrepareOp, SqeExt},
-    IoUring, Result,
-};
-use std::sync::{Arc, Mutex};
+use super::driver::{self, AsyncIoReadiness, Driver, OpFuture, Reactor};
+use super::multishot::Multishot;
+use crate::{ops::PrepareOp, Result};
+use std::future::Future;
+use std::sync::Arc;
 
 /// Async io_uring instance integrated with async-std runtime
 ///
 /// This wraps an `IoUring` instance and integrates it with async-std's async runtime,
 /// allowing you to use async/await with io_uring operations.
 ///
+/// Completions are dispatched the same way as in the tokio backend: a
+/// reactor task submits queued SQEs in batches and hands every CQE to the
+/// operation it belongs to, waiting on the ring fd through `async-io` in
+/// between. Handles are cheap to clone.
+///
 /// # Example
 ///
 /// ```no_run
@@ -18,7 +23,7 @@ use std::sync::{Arc, Mutex};
 /// use liburing_rs::async_io::AsyncIoUring;
 /// use liburing_rs::ops::Nop;
 ///
-/// let mut ring = AsyncIoUring::new(32)?;
+/// let ring = AsyncIoUring::new(32)?;
 ///
 /// // Submit a NOP operation and await its completion
 /// let result = ring.submit_op(Nop).await?;
@@ -26,8 +31,9 @@ use std::sync::{Arc, Mutex};
 /// # Ok::<(), liburing_rs::Error>(())
 /// # }).unwrap();
 /// ```
+#[derive(Clone)]
 pub struct AsyncIoUring {
-    ring: Arc<Mutex<IoUring>>,
+    reactor: Arc<Reactor<AsyncIoReadiness>>,
 }
 
 impl AsyncIoUring {
@@ -41,14 +47,38 @@ impl AsyncIoUring {
     ///
     /// Returns an error if the kernel doesn't support io_uring or if setup fails.
     pub fn new(entries: u32) -> Result<Self> {
-        let ring = IoUring::new(entries)?;
-        Ok(Self {
-            ring: Arc::new(Mutex::new(ring)),
-        })
+        Self::setup(entries, false)
+    }
+
+    /// Create a new async io_uring instance that is woken through an eventfd
+    ///
+    /// The eventfd is registered with the ring and polled in place of the
+    /// ring fd.
+    pub fn with_eventfd(entries: u32) -> Result<Self> {
+        Self::setup(entries, true)
+    }
+
+    fn setup(entries: u32, use_eventfd: bool) -> Result<Self> {
+        let (ring, eventfd) = driver::setup_ring(entries, use_eventfd)?;
+        let driver = Driver::new(ring, eventfd);
+        let readiness = AsyncIoReadiness::new(&driver)?;
+
+        let reactor = Arc::new(Reactor::new(readiness, driver));
+        async_std::task::spawn(driver::run_reactor(Arc::downgrade(&reactor)));
+
+        Ok(Self { reactor })
     }
 
     /// Submit an operation and wait for its completion asynchronously
     ///
+    /// The SQE is queued on the first poll and submitted together with the
+    /// other SQEs queued before the reactor next runs.
+    ///
+    /// The op is owned by the future until its CQE arrives, so any buffer
+    /// it points into stays valid. If the future is dropped while the op is
+    /// in flight, the op is cancelled with `IORING_OP_ASYNC_CANCEL` and kept
+    /// alive by the driver until the kernel posts its final completion.
+    ///
     /// # Arguments
     ///
     /// * `op` - The operation to submit (implements `PrepareOp`)
@@ -56,33 +86,39 @@ impl AsyncIoUring {
     /// # Returns
     ///
     /// A future that resolves to the result code of the operation
-    pub async fn submit_op<Op: PrepareOp + Send + 'static>(&mut self, op: Op) -> Result<i32> {
-        let ring = self.ring.clone();
+    pub fn submit_op<Op: PrepareOp + Send + 'static>(
+        &self,
+        op: Op,
+    ) -> impl Future<Output = Result<i32>> {
+        let op = OpFuture::new(self.reactor.clone(), op);
+        async move { op.await.map(|(completion, _)| completion.result()) }
+    }
 
-        async_std::task::spawn_blocking(move || {
-            let mut ring = ring.lock().unwrap();
+    /// Submit a multishot operation and stream its completions
+    ///
+    /// Every CQE the op posts is yielded in order; the stream ends with the
+    /// one without `IORING_CQE_F_MORE`. Call [`Multishot::rearm`] on the
+    /// result to keep the op armed instead. Dropping the stream cancels the
+    /// op.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The multishot operation to submit (implements `PrepareOp`)
+    pub fn submit_multishot<Op: PrepareOp + Send + 'static>(&self, op: Op) -> Multishot<Op> {
+        Multishot::new(self.handle(), op)
+    }
 
-            // Submit the operation
-            let user_data = 1u64;
-            {
-                let mut sq = ring.submission();
-                let sqe = sq.get_sqe_or_err()?;
-                op.prepare(sqe);
-                sqe.set_user_data(user_data);
-            }
+    pub(crate) fn handle(&self) -> driver::Handle {
+        self.reactor.clone()
+    }
+}
 
-            // Submit to kernel
-            ring.submit()?;
+impl super::sealed::Sealed for AsyncIoUring {
+    type Handle = super::SharedHandle;
 
-            // Wait for completion
-            let mut cq = ring.completion();
-            let cqe = cq.wait_cqe()?;
-            Ok(cqe.result())
-        })
-        .await
+    fn ring_handle(&self) -> super::SharedHandle {
+        super::SharedHandle(self.handle())
     }
 }
 
-// AsyncIoUring can be sent between threads
-unsafe impl Send for AsyncIoUring {}
-unsafe impl Sync for AsyncIoUring {}
+impl super::Ring for AsyncIoUring {}
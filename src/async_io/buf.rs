@@ -0,0 +1,192 @@
+//! Operations that own their buffers
+//!
+//! The driver keeps an op alive until the kernel has posted its final CQE,
+//! even when the future awaiting it is dropped, so these can hand the
+//! kernel a pointer into a buffer they own.
+
+use crate::{ops::PrepareOp, sys, Completion};
+use std::io;
+use std::os::unix::io::RawFd;
+
+/// Offset that makes a read or write use and advance the file position
+pub(crate) const CURRENT_POSITION: u64 = u64::MAX;
+
+/// Buffer reused by the stream traits, holding data read ahead of the caller
+#[derive(Default)]
+pub(crate) struct Buf {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Buf {
+    pub(crate) fn new(data: Vec<u8>) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Bytes read ahead and not yet returned
+    pub(crate) fn unread(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub(crate) fn copy_to(&mut self, dst: &mut [u8]) -> usize {
+        let n = self.unread().min(dst.len());
+        dst[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.data.clear();
+        self.pos = 0;
+    }
+
+    /// Take the allocation for the next op
+    pub(crate) fn take(&mut self) -> Vec<u8> {
+        self.clear();
+        std::mem::take(&mut self.data)
+    }
+}
+
+/// Read into the spare capacity of a buffer
+pub(crate) struct ReadBuf {
+    fd: RawFd,
+    buf: Vec<u8>,
+    // Taken while `buf` is borrowed mutably; the heap block never moves
+    ptr: *mut u8,
+    len: u32,
+    // `None` receives from a socket instead
+    offset: Option<u64>,
+}
+
+// SAFETY: `ptr` points into `buf`, which the op owns
+unsafe impl Send for ReadBuf {}
+unsafe impl Sync for ReadBuf {}
+
+impl ReadBuf {
+    /// Read up to `len` bytes past the end of `buf`'s contents
+    pub(crate) fn new(fd: RawFd, buf: Vec<u8>, len: usize, offset: u64) -> Self {
+        Self::with_offset(fd, buf, len, Some(offset))
+    }
+
+    /// Receive up to `len` bytes past the end of `buf`'s contents
+    pub(crate) fn recv(fd: RawFd, buf: Vec<u8>, len: usize) -> Self {
+        Self::with_offset(fd, buf, len, None)
+    }
+
+    fn with_offset(fd: RawFd, mut buf: Vec<u8>, len: usize, offset: Option<u64>) -> Self {
+        let len = len.min(u32::MAX as usize);
+        buf.reserve(len);
+        let ptr = unsafe { buf.as_mut_ptr().add(buf.len()) };
+        Self {
+            fd,
+            buf,
+            ptr,
+            len: len as u32,
+            offset,
+        }
+    }
+
+    /// Take the buffer back, extended by the bytes read
+    pub(crate) fn finish(self, completion: Completion) -> (io::Result<usize>, Vec<u8>) {
+        let mut buf = self.buf;
+        let res = completion.into_result().map(|n| {
+            let n = n as usize;
+            // The kernel initialized `n` bytes past the old length
+            unsafe { buf.set_len(buf.len() + n) };
+            n
+        });
+        (res, buf)
+    }
+}
+
+impl PrepareOp for ReadBuf {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            match self.offset {
+                Some(offset) => {
+                    sys::io_uring_prep_read(sqe, self.fd, self.ptr as *mut _, self.len, offset)
+                }
+                None => sys::io_uring_prep_recv(sqe, self.fd, self.ptr as *mut _, self.len as _, 0),
+            }
+        }
+    }
+}
+
+/// Write the part of a buffer that hasn't been written yet
+pub(crate) struct WriteBuf {
+    fd: RawFd,
+    buf: Vec<u8>,
+    written: usize,
+    // `None` sends on a socket instead
+    offset: Option<u64>,
+}
+
+impl WriteBuf {
+    pub(crate) fn new(fd: RawFd, buf: Vec<u8>, offset: u64) -> Self {
+        Self {
+            fd,
+            buf,
+            written: 0,
+            offset: Some(offset),
+        }
+    }
+
+    /// Send `buf` without raising `SIGPIPE` if the peer went away
+    pub(crate) fn send(fd: RawFd, buf: Vec<u8>) -> Self {
+        Self {
+            fd,
+            buf,
+            written: 0,
+            offset: None,
+        }
+    }
+
+    /// Account for a completed write, returning the bytes it wrote
+    ///
+    /// A zero-length write of a non-empty remainder is reported as
+    /// [`io::ErrorKind::WriteZero`].
+    pub(crate) fn advance(&mut self, completion: Completion) -> io::Result<usize> {
+        let n = completion.into_result()? as usize;
+        if n == 0 && !self.is_done() {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        self.written += n;
+        if let Some(offset) = self.offset.as_mut().filter(|o| **o != CURRENT_POSITION) {
+            *offset += n as u64;
+        }
+        Ok(n)
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.written >= self.buf.len()
+    }
+
+    pub(crate) fn into_buf(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl PrepareOp for WriteBuf {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        let rest = &self.buf[self.written..];
+        let len = rest.len().min(u32::MAX as usize);
+        unsafe {
+            match self.offset {
+                Some(offset) => sys::io_uring_prep_write(
+                    sqe,
+                    self.fd,
+                    rest.as_ptr() as *const _,
+                    len as u32,
+                    offset,
+                ),
+                None => sys::io_uring_prep_send(
+                    sqe,
+                    self.fd,
+                    rest.as_ptr() as *const _,
+                    len,
+                    libc::MSG_NOSIGNAL,
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,1213 @@
+//! Completion dispatch shared by the async backends
+//!
+//! Every in-flight operation owns a slot keyed by its `user_data`. A single
+//! reactor per ring submits the SQEs queued since its last run, drains the
+//! CQ, parks each result in its slot and wakes the task that owns it. The
+//! backends only differ in how the reactor learns that the ring is readable.
+//!
+//! Ops are boxed on creation so their SQEs may point into them. If an op's
+//! future is dropped while it is in flight, an `ASYNC_CANCEL` is queued for
+//! it and the box is parked in its slot until the final CQE arrives, so the
+//! kernel never writes into freed memory.
+//!
+//! The core sits behind a `Mutex` for rings shared between threads, or a
+//! `RefCell` for rings that never leave the thread that set them up.
+//!
+//! A thread blocked waiting for CQEs doesn't hold the core, and only one
+//! thread does so at a time. Another thread that queues SQEs or reaps CQEs
+//! meanwhile submits a NOP, whose CQE ends the wait.
+
+use crate::{
+    flags::{SetupFlags, SqeFlags},
+    ops::{LinkTimeout, PrepareOp, SqeExt},
+    sys,
+    types::EventFd,
+    BackpressurePolicy, Completion, Error, IoUring, IoUringBuilder, Result,
+};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{ready, Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// user_data of the SQEs whose CQEs nobody waits for: the `ASYNC_CANCEL`s
+/// queued for dropped ops, and linked timeouts
+const UNTRACKED_USER_DATA: u64 = u64::MAX;
+
+/// user_data of the NOPs that end the wait of a blocked thread
+const WAKE_USER_DATA: u64 = u64::MAX - 1;
+
+thread_local! {
+    // Deadline of the `time::timeout` being polled on this thread, if any
+    static DEADLINE: RefCell<Option<Deadline>> = const { RefCell::new(None) };
+}
+
+/// State of an in-flight operation
+enum Slot {
+    /// Waiting for its CQE, with the waker of the task awaiting it
+    Waiting(Option<Waker>),
+    /// The CQE arrived before the owner polled again
+    Completed(Completion),
+    /// A multishot op, with the CQEs its owner hasn't taken yet
+    Streaming {
+        waker: Option<Waker>,
+        cqes: VecDeque<Completion>,
+    },
+    /// The future was dropped; the op is kept alive until its final CQE,
+    /// and the CQEs it still posts go to `on_cqe`
+    Orphaned {
+        _op: Box<dyn Any + Send>,
+        on_cqe: Option<fn(Completion)>,
+    },
+}
+
+impl Slot {
+    /// Whether the kernel may still post CQEs for the op
+    fn in_flight(&self) -> bool {
+        match self {
+            Slot::Waiting(_) | Slot::Orphaned { .. } => true,
+            Slot::Streaming { cqes, .. } => cqes.back().map_or(true, |c| c.has_more()),
+            Slot::Completed(_) => false,
+        }
+    }
+}
+
+/// Ring and slot table, without any locking
+pub struct Core {
+    ring: IoUring,
+    slots: HashMap<u64, Slot>,
+    next_user_data: u64,
+    // SQEs prepared since the last submit
+    queued: u32,
+    // Woken when the first SQE of a batch is queued
+    reactor: Option<Waker>,
+    // Why the reactor stopped, if it did
+    stopped: Option<String>,
+    // Completions wait for task work that only runs when asked for
+    defer_taskrun: bool,
+    // A thread is blocked in `Waiter::wait`
+    waiting: bool,
+    // A NOP to end that wait was submitted and its CQE not reaped yet
+    wake_sent: bool,
+}
+
+impl Core {
+    pub(crate) fn new(ring: IoUring) -> Self {
+        let defer_taskrun = ring.setup_flags().contains(SetupFlags::DEFER_TASKRUN);
+        Self {
+            ring,
+            slots: HashMap::new(),
+            next_user_data: 1,
+            queued: 0,
+            reactor: None,
+            stopped: None,
+            defer_taskrun,
+            waiting: false,
+            wake_sent: false,
+        }
+    }
+
+    /// Prepare an SQE for `op` and give it a slot
+    ///
+    /// The SQE goes out with the reactor's next submit. Returns the op's
+    /// user_data, and the reactor's waker if it has to be woken for that.
+    /// A `multishot` op keeps every CQE it posts until they are taken. With
+    /// a `link_timeout`, the kernel cancels the op once it expires.
+    fn push<Op: PrepareOp>(
+        &mut self,
+        op: &Op,
+        link_timeout: Option<&LinkTimeout>,
+        waker: &Waker,
+        multishot: bool,
+    ) -> Result<(u64, Option<Waker>)> {
+        if let Some(reason) = &self.stopped {
+            return Err(stopped(reason));
+        }
+
+        let user_data = self.next_user_data();
+        {
+            let mut sq = self.ring.submission();
+            if link_timeout.is_some() && sq.space_left() < 2 {
+                // A flush between the two would cut the link, and the op's
+                // SQE can't be left behind linked to nothing
+                sq.submit()?;
+                if sq.space_left() < 2 {
+                    return Err(Error::SubmissionQueueFull);
+                }
+            }
+            let sqe = sq.get_sqe_or_err()?;
+            op.prepare(sqe);
+            sqe.set_user_data(user_data);
+            if let Some(timeout) = link_timeout {
+                sqe.flags |= SqeFlags::IO_LINK.bits();
+                let sqe = sq.get_sqe_or_err()?;
+                timeout.prepare(sqe);
+                sqe.set_user_data(UNTRACKED_USER_DATA);
+            }
+        }
+        let slot = if multishot {
+            Slot::Streaming {
+                waker: Some(waker.clone()),
+                cqes: VecDeque::new(),
+            }
+        } else {
+            Slot::Waiting(Some(waker.clone()))
+        };
+        self.slots.insert(user_data, slot);
+
+        let reactor = self.queue();
+        // The SQE is in the ring already; a failed wake only leaves the
+        // waiter blocked until its next CQE
+        let _ = self.wake_waiter();
+        Ok((user_data, reactor))
+    }
+
+    /// Count a newly prepared SQE, returning the reactor's waker if this is
+    /// the first one of a batch
+    fn queue(&mut self) -> Option<Waker> {
+        self.queued += 1;
+        if self.queued == 1 {
+            self.reactor.clone()
+        } else {
+            None
+        }
+    }
+
+    fn next_user_data(&mut self) -> u64 {
+        loop {
+            let user_data = self.next_user_data;
+            self.next_user_data = self.next_user_data.wrapping_add(1).max(1);
+            if user_data < WAKE_USER_DATA && !self.slots.contains_key(&user_data) {
+                return user_data;
+            }
+        }
+    }
+
+    /// Take the result of an op, or remember who to wake when it arrives
+    ///
+    /// Once the driver has stopped, a pending op resolves with an error but
+    /// keeps its slot: the kernel may still write into the op, so its owner
+    /// has to hand it over with [`cancel`](Self::cancel).
+    fn poll_slot(&mut self, user_data: u64, cx: &mut Context<'_>) -> Poll<Result<Completion>> {
+        match self.slots.get_mut(&user_data) {
+            Some(Slot::Completed(completion)) => {
+                let completion = *completion;
+                self.slots.remove(&user_data);
+                Poll::Ready(Ok(completion))
+            }
+            Some(Slot::Waiting(waker)) => {
+                if let Some(reason) = &self.stopped {
+                    return Poll::Ready(Err(stopped(reason)));
+                }
+                register(waker, cx);
+                Poll::Pending
+            }
+            _ => Poll::Ready(Err(no_slot(user_data))),
+        }
+    }
+
+    /// Take the next CQE of a multishot op
+    ///
+    /// The slot goes away with the op's final CQE, the one without
+    /// `IORING_CQE_F_MORE`. As with [`poll_slot`](Self::poll_slot), a
+    /// stopped driver leaves the slot for the op to be handed over.
+    fn poll_stream(&mut self, user_data: u64, cx: &mut Context<'_>) -> Poll<Result<Completion>> {
+        let Some(Slot::Streaming { waker, cqes }) = self.slots.get_mut(&user_data) else {
+            return Poll::Ready(Err(no_slot(user_data)));
+        };
+        if let Some(completion) = cqes.pop_front() {
+            if !completion.has_more() {
+                self.slots.remove(&user_data);
+            }
+            return Poll::Ready(Ok(completion));
+        }
+        if let Some(reason) = &self.stopped {
+            return Poll::Ready(Err(stopped(reason)));
+        }
+        register(waker, cx);
+        Poll::Pending
+    }
+
+    /// Handle the future of an op going away
+    ///
+    /// If the op is still in flight it is cancelled, and `op` is parked
+    /// until the kernel is done with it. The CQEs nobody will take, those
+    /// that already arrived and any posted later, are handed to `on_cqe`.
+    /// Returns the reactor's waker if the cancel SQE needs submitting.
+    fn cancel(
+        &mut self,
+        user_data: u64,
+        op: Box<dyn Any + Send>,
+        on_cqe: Option<fn(Completion)>,
+    ) -> Option<Waker> {
+        let slot = self.slots.get_mut(&user_data)?;
+        let in_flight = slot.in_flight();
+        match slot {
+            Slot::Completed(completion) => {
+                if let Some(on_cqe) = on_cqe {
+                    on_cqe(*completion);
+                }
+            }
+            Slot::Streaming { cqes, .. } => {
+                if let Some(on_cqe) = on_cqe {
+                    cqes.drain(..).for_each(on_cqe);
+                }
+            }
+            Slot::Waiting(_) => {}
+            // Only one future owns an op
+            Slot::Orphaned { .. } => unreachable!(),
+        }
+        if !in_flight {
+            self.slots.remove(&user_data);
+            return None;
+        }
+        *slot = Slot::Orphaned { _op: op, on_cqe };
+
+        // Without a free SQE the op is simply left to finish on its own
+        let mut sq = self.ring.submission();
+        let Ok(sqe) = sq.get_sqe_or_err() else {
+            return None;
+        };
+        unsafe { sys::io_uring_prep_cancel64(sqe, user_data, 0) };
+        sqe.set_user_data(UNTRACKED_USER_DATA);
+        let reactor = self.queue();
+        let _ = self.wake_waiter();
+        reactor
+    }
+
+    /// Move a CQE into its op's slot, returning the waker to wake
+    fn dispatch(slots: &mut HashMap<u64, Slot>, completion: Completion) -> Option<Waker> {
+        let user_data = completion.user_data();
+        match slots.get_mut(&user_data)? {
+            slot @ Slot::Waiting(_) => {
+                let Slot::Waiting(waker) = std::mem::replace(slot, Slot::Completed(completion))
+                else {
+                    unreachable!()
+                };
+                waker
+            }
+            Slot::Streaming { waker, cqes } => {
+                cqes.push_back(completion);
+                waker.take()
+            }
+            Slot::Orphaned { on_cqe, .. } => {
+                if let Some(on_cqe) = on_cqe {
+                    on_cqe(completion);
+                }
+                if !completion.has_more() {
+                    slots.remove(&user_data);
+                }
+                None
+            }
+            // Later CQEs of an op that already resolved
+            Slot::Completed(_) => None,
+        }
+    }
+
+    fn has_orphans(&self) -> bool {
+        self.slots
+            .values()
+            .any(|slot| matches!(slot, Slot::Orphaned { .. }))
+    }
+
+    /// Submit queued SQEs and dispatch every available CQE
+    ///
+    /// The wakers of completed ops are pushed to `wakers`, to be woken once
+    /// the caller no longer holds the core.
+    fn tick(&mut self, reactor: &Waker, wakers: &mut Vec<Waker>) -> Result<()> {
+        match &self.reactor {
+            Some(waker) if waker.will_wake(reactor) => {}
+            _ => self.reactor = Some(reactor.clone()),
+        }
+
+        if self.queued > 0 && !self.submit(0)? {
+            // Try again on the next tick
+            wakers.push(reactor.clone());
+        }
+        if self.defer_taskrun && !self.run_task_work()? {
+            wakers.push(reactor.clone());
+        }
+        if self.dispatch_all(wakers) > 0 {
+            // The waiter may have been waiting for one of them
+            self.wake_waiter()?;
+        }
+        Ok(())
+    }
+
+    /// Submit queued SQEs and dispatch the CQEs already posted
+    ///
+    /// Returns how to wait for the next CQE if ops are in flight and none
+    /// of them completed yet; the wait is left to the caller, so that it
+    /// need not hold the core meanwhile. The caller becomes the waiter
+    /// until it calls [`end_wait`](Self::end_wait).
+    fn drive(&mut self, wakers: &mut Vec<Waker>) -> Result<Option<Waiter>> {
+        let submitted = self.queued == 0 || self.submit(0)?;
+        self.dispatch_all(wakers);
+
+        let idle = wakers.is_empty() && self.slots.values().any(Slot::in_flight);
+        if !(submitted && idle) {
+            return Ok(None);
+        }
+        self.waiting = true;
+        self.wake_sent = false;
+        Ok(Some(Waiter::new(&self.ring)))
+    }
+
+    /// Dispatch what arrived while the waiter was blocked
+    fn end_wait(&mut self, wakers: &mut Vec<Waker>) {
+        self.waiting = false;
+        self.dispatch_all(wakers);
+    }
+
+    /// Submit a NOP whose CQE ends the wait of a blocked thread, along with
+    /// the SQEs queued so far
+    ///
+    /// Called when SQEs are queued or CQEs reaped while a thread waits:
+    /// the first would otherwise only go out once it returns, and the CQE
+    /// it waits for may be among the second.
+    fn wake_waiter(&mut self) -> Result<()> {
+        if !self.waiting || self.wake_sent {
+            return Ok(());
+        }
+        let mut sq = self.ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        unsafe { sys::io_uring_prep_nop(sqe) };
+        sqe.set_user_data(WAKE_USER_DATA);
+        self.queued += 1;
+        self.wake_sent = self.submit(0)?;
+        Ok(())
+    }
+
+    /// Submit queued SQEs and wait for `wait` CQEs
+    ///
+    /// Returns false if the kernel asked to be retried later.
+    fn submit(&mut self, wait: u32) -> Result<bool> {
+        let submitted = retry_later(self.ring.submit_and_wait(wait).map(drop))?;
+        if submitted {
+            self.queued = 0;
+        }
+        Ok(submitted)
+    }
+
+    /// Run the task work the kernel deferred, posting the CQEs it produces
+    ///
+    /// Waiting in `submit` does this too; only non-blocking reaps need it.
+    /// Returns false if the kernel asked to be retried later.
+    fn run_task_work(&mut self) -> Result<bool> {
+        retry_later(self.ring.get_events())
+    }
+
+    /// Dispatch every available CQE, returning how many there were
+    fn dispatch_all(&mut self, wakers: &mut Vec<Waker>) -> usize {
+        let mut reaped = 0;
+        for completion in self.ring.completion().drain() {
+            if completion.user_data() == WAKE_USER_DATA {
+                self.wake_sent = false;
+            }
+            wakers.extend(Self::dispatch(&mut self.slots, completion));
+            reaped += 1;
+        }
+        reaped
+    }
+
+    /// Fail every pending op, and any op pushed from now on
+    fn stop(&mut self, err: &Error, wakers: &mut Vec<Waker>) {
+        self.stopped = Some(err.to_string());
+        for slot in self.slots.values_mut() {
+            if let Slot::Waiting(waker) | Slot::Streaming { waker, .. } = slot {
+                wakers.extend(waker.take());
+            }
+        }
+    }
+}
+
+/// An `io_uring_enter` that waits for a CQE, made without the core
+struct Waiter {
+    fd: u32,
+    flags: u32,
+}
+
+impl Waiter {
+    fn new(ring: &IoUring) -> Self {
+        let raw = ring.as_raw();
+        // Without its plain fd, the ring is only reachable through the
+        // registered one
+        if raw.ring_fd >= 0 {
+            Self {
+                fd: raw.ring_fd as u32,
+                flags: sys::IORING_ENTER_GETEVENTS,
+            }
+        } else {
+            Self {
+                fd: raw.enter_ring_fd as u32,
+                flags: sys::IORING_ENTER_GETEVENTS | sys::IORING_ENTER_REGISTERED_RING,
+            }
+        }
+    }
+
+    /// Block until the CQ holds at least one CQE
+    fn wait(&self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_enter(self.fd, 0, 1, self.flags, std::ptr::null_mut()) };
+        let res = if ret < 0 {
+            Err(crate::error::from_ret_code(ret).into())
+        } else {
+            Ok(())
+        };
+        retry_later(res).map(drop)
+    }
+}
+
+impl Drop for Core {
+    fn drop(&mut self) {
+        // Closing the ring doesn't wait for requests to finish, so reap the
+        // parked ops first rather than free memory the kernel may still use
+        while self.has_orphans() {
+            // Cancels are re-sent each round in case one didn't fit in the
+            // SQ before; cancelling twice is harmless
+            let mut sq = self.ring.submission();
+            for (&user_data, _) in self
+                .slots
+                .iter()
+                .filter(|(_, slot)| matches!(slot, Slot::Orphaned { .. }))
+            {
+                let Ok(sqe) = sq.get_sqe_or_err() else {
+                    break;
+                };
+                unsafe { sys::io_uring_prep_cancel64(sqe, user_data, 0) };
+                sqe.set_user_data(UNTRACKED_USER_DATA);
+            }
+
+            if self.ring.submit_and_wait(1).is_err() {
+                // The kernel may still write into them; leak them instead
+                for (_, slot) in self.slots.drain() {
+                    if let Slot::Orphaned { _op, .. } = slot {
+                        std::mem::forget(_op);
+                    }
+                }
+                break;
+            }
+            for completion in self.ring.completion().drain() {
+                Self::dispatch(&mut self.slots, completion);
+            }
+        }
+    }
+}
+
+/// Exclusive access to a [`Core`]
+pub trait CoreCell {
+    fn new(core: Core) -> Self;
+
+    /// Run `f` on the core; wakers must only be woken once it returns
+    fn with<R>(&self, f: impl FnOnce(&mut Core) -> R) -> R;
+
+    /// Like [`with`](Self::with), unless another thread is waiting for
+    /// CQEs; then block until it's done instead, and return `None`
+    fn with_turn<R>(&self, f: impl FnOnce(&mut Core) -> R) -> Option<R>;
+
+    /// Run `f` on the core, then let the threads blocked in
+    /// [`with_turn`](Self::with_turn) go
+    fn end_turn<R>(&self, f: impl FnOnce(&mut Core) -> R) -> R;
+
+    fn get_mut(&mut self) -> &mut Core;
+}
+
+/// Core of a ring shared between threads
+pub struct SharedCore {
+    core: Mutex<Core>,
+    // Signalled when the waiter returns
+    turn: Condvar,
+}
+
+impl CoreCell for SharedCore {
+    fn new(core: Core) -> Self {
+        Self {
+            core: Mutex::new(core),
+            turn: Condvar::new(),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Core) -> R) -> R {
+        f(&mut self.core.lock().unwrap())
+    }
+
+    fn with_turn<R>(&self, f: impl FnOnce(&mut Core) -> R) -> Option<R> {
+        let mut core = self.core.lock().unwrap();
+        if !core.waiting {
+            return Some(f(&mut core));
+        }
+        while core.waiting {
+            core = self.turn.wait(core).unwrap();
+        }
+        None
+    }
+
+    fn end_turn<R>(&self, f: impl FnOnce(&mut Core) -> R) -> R {
+        let ret = self.with(f);
+        self.turn.notify_all();
+        ret
+    }
+
+    fn get_mut(&mut self) -> &mut Core {
+        self.core.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CoreCell for RefCell<Core> {
+    fn new(core: Core) -> Self {
+        RefCell::new(core)
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Core) -> R) -> R {
+        f(&mut self.borrow_mut())
+    }
+
+    fn with_turn<R>(&self, f: impl FnOnce(&mut Core) -> R) -> Option<R> {
+        // The waiter blocks the only thread that could ask
+        Some(self.with(f))
+    }
+
+    fn end_turn<R>(&self, f: impl FnOnce(&mut Core) -> R) -> R {
+        self.with(f)
+    }
+
+    fn get_mut(&mut self) -> &mut Core {
+        RefCell::get_mut(self)
+    }
+}
+
+/// Handle to a [`Core`], thread-safe unless `C` is a `RefCell`
+pub struct Driver<C: CoreCell = SharedCore> {
+    core: C,
+    // When set, readiness comes from this eventfd instead of the ring fd
+    eventfd: Option<EventFd>,
+}
+
+/// Driver of a ring that stays on one thread
+pub type LocalDriver = Driver<RefCell<Core>>;
+
+impl<C: CoreCell> Driver<C> {
+    pub(crate) fn new(ring: IoUring, eventfd: Option<EventFd>) -> Self {
+        Self {
+            core: C::new(Core::new(ring)),
+            eventfd,
+        }
+    }
+
+    /// The fd to watch for readability: the eventfd if there is one, else
+    /// the ring fd
+    pub(crate) fn readiness_fd(&self) -> RingFd {
+        match &self.eventfd {
+            Some(eventfd) => RingFd(eventfd.as_raw_fd()),
+            None => RingFd(self.core.with(|core| core.ring.as_raw_fd())),
+        }
+    }
+
+    /// Queue the SQE of an op for the reactor's next submit
+    fn push<Op: PrepareOp>(
+        &self,
+        op: &Op,
+        link_timeout: Option<&LinkTimeout>,
+        waker: &Waker,
+        multishot: bool,
+    ) -> Result<u64> {
+        let (user_data, reactor) = self
+            .core
+            .with(|core| core.push(op, link_timeout, waker, multishot))?;
+        if let Some(reactor) = reactor {
+            reactor.wake();
+        }
+        Ok(user_data)
+    }
+
+    fn poll_slot(&self, user_data: u64, cx: &mut Context<'_>) -> Poll<Result<Completion>> {
+        self.core.with(|core| core.poll_slot(user_data, cx))
+    }
+
+    fn poll_stream(&self, user_data: u64, cx: &mut Context<'_>) -> Poll<Result<Completion>> {
+        self.core.with(|core| core.poll_stream(user_data, cx))
+    }
+
+    fn cancel(&self, user_data: u64, op: Box<dyn Any + Send>, on_cqe: Option<fn(Completion)>) {
+        let reactor = self.core.with(|core| core.cancel(user_data, op, on_cqe));
+        if let Some(reactor) = reactor {
+            reactor.wake();
+        }
+    }
+
+    /// One reactor step; `reactor` is woken when new SQEs are queued
+    fn tick(&self, reactor: &Waker) -> Result<()> {
+        let mut wakers = Vec::new();
+        let ret = self.core.with(|core| core.tick(reactor, &mut wakers));
+        wakers.into_iter().for_each(Waker::wake);
+        ret
+    }
+
+    /// Stop dispatching, failing all pending ops with `err`
+    fn stop(&self, err: &Error) {
+        let mut wakers = Vec::new();
+        self.core.with(|core| core.stop(err, &mut wakers));
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Submit queued SQEs and dispatch the CQEs that are already there
+    ///
+    /// Never completes unless the ring fails. `cx` is woken when new SQEs
+    /// are queued; arriving CQEs are signalled by the readiness fd.
+    pub(crate) fn poll_completions(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // As in `drive`, reset before dispatching so no signal is lost
+        self.reset_eventfd();
+        if let Err(e) = self.tick(cx.waker()) {
+            self.stop(&e);
+            return Poll::Ready(Err(e));
+        }
+        Poll::Pending
+    }
+
+    /// Submit queued SQEs and block until at least one op completes
+    ///
+    /// Returns right away if no op is in flight. The core is not held
+    /// while blocked, so other threads can queue and submit ops meanwhile;
+    /// doing so ends the wait early. If another thread is blocked already,
+    /// this waits for it to return instead.
+    pub(crate) fn drive(&self) -> Result<()> {
+        // Only signals for CQEs that are dispatched below are consumed
+        self.reset_eventfd();
+        let mut wakers = Vec::new();
+        let ret = self.wait_and_dispatch(&mut wakers);
+        wakers.into_iter().for_each(Waker::wake);
+        if let Err(e) = &ret {
+            self.stop(e);
+        }
+        ret
+    }
+
+    fn wait_and_dispatch(&self, wakers: &mut Vec<Waker>) -> Result<()> {
+        // When another thread waited, it dispatched whatever arrived
+        let Some(waiter) = self.core.with_turn(|core| core.drive(wakers)) else {
+            return Ok(());
+        };
+        if let Some(waiter) = waiter? {
+            let ret = waiter.wait();
+            self.core.end_turn(|core| core.end_wait(wakers));
+            ret?;
+        }
+        Ok(())
+    }
+
+    fn reset_eventfd(&self) {
+        if let Some(eventfd) = &self.eventfd {
+            let _ = eventfd.read();
+        }
+    }
+}
+
+impl AsRef<Driver> for Driver {
+    fn as_ref(&self) -> &Driver {
+        self
+    }
+}
+
+impl<C: CoreCell> Drop for Driver<C> {
+    fn drop(&mut self) {
+        // Let the reactor notice that the ring is gone
+        if let Some(reactor) = self.core.get_mut().reactor.take() {
+            reactor.wake();
+        }
+    }
+}
+
+/// Create the ring behind an async backend, optionally with an eventfd
+pub(crate) fn setup_ring(entries: u32, use_eventfd: bool) -> Result<(IoUring, Option<EventFd>)> {
+    setup_ring_with(IoUring::builder(), entries, use_eventfd)
+}
+
+/// Like [`setup_ring`], with extra setup flags from `builder`
+pub(crate) fn setup_ring_with(
+    builder: IoUringBuilder,
+    entries: u32,
+    use_eventfd: bool,
+) -> Result<(IoUring, Option<EventFd>)> {
+    let mut ring = builder
+        .backpressure(BackpressurePolicy::FlushAndRetry)
+        .build(entries)?;
+
+    let eventfd = if use_eventfd {
+        let eventfd = EventFd::new()?;
+        ring.register_eventfd(&eventfd)?;
+        Some(eventfd)
+    } else {
+        None
+    };
+    Ok((ring, eventfd))
+}
+
+/// Borrowed ring or eventfd, registered with a runtime's I/O reactor
+pub(crate) struct RingFd(RawFd);
+
+impl AsRawFd for RingFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl AsFd for RingFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // The fd is owned by the driver, which outlives the registration
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/// A runtime's way of waiting for the ring to become readable
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+pub(crate) trait Readiness {
+    /// Resolve once the watched fd is readable, consuming that readiness
+    fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>>;
+}
+
+/// Readiness through `async-io`, shared by the async-std and smol backends
+#[cfg(feature = "async-io")]
+pub(crate) struct AsyncIoReadiness(async_io::Async<RingFd>);
+
+#[cfg(feature = "async-io")]
+impl AsyncIoReadiness {
+    pub(crate) fn new(driver: &Driver) -> Result<Self> {
+        // The ring fd is never read, and the eventfd is already non-blocking
+        let source = async_io::Async::new_nonblocking(driver.readiness_fd()).map_err(Error::Io)?;
+        Ok(Self(source))
+    }
+}
+
+#[cfg(feature = "async-io")]
+impl Readiness for AsyncIoReadiness {
+    fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.0.poll_readable(cx)
+    }
+}
+
+/// A driver together with the readiness source of its backend
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+pub(crate) struct Reactor<R> {
+    // Declared first so it is deregistered before the fd it watches closes
+    readiness: R,
+    driver: Driver,
+}
+
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+impl<R: Readiness> Reactor<R> {
+    pub(crate) fn new(readiness: R, driver: Driver) -> Self {
+        Self { readiness, driver }
+    }
+
+    /// Submit, dispatch completions, and wait for the ring to become readable
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Err(e) = self.driver.tick(cx.waker()) {
+                self.driver.stop(&e);
+                return Poll::Ready(());
+            }
+
+            match self.readiness.poll_readable(cx) {
+                Poll::Ready(Ok(())) => {
+                    // Reset the eventfd counter, then dispatch whatever
+                    // arrived in the meantime
+                    self.driver.reset_eventfd();
+                }
+                Poll::Ready(Err(e)) => {
+                    self.driver.stop(&Error::Io(e));
+                    return Poll::Ready(());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+impl<R> AsRef<Driver> for Reactor<R> {
+    fn as_ref(&self) -> &Driver {
+        &self.driver
+    }
+}
+
+/// Reactor task, running until the last handle to the ring is dropped
+#[cfg(any(feature = "async-tokio", feature = "async-io"))]
+pub(crate) async fn run_reactor<R: Readiness>(reactor: std::sync::Weak<Reactor<R>>) {
+    std::future::poll_fn(|cx| match reactor.upgrade() {
+        Some(reactor) => reactor.poll(cx),
+        None => Poll::Ready(()),
+    })
+    .await
+}
+
+/// A reactor with its readiness source erased
+pub type DynReactor = dyn AsRef<Driver> + Send + Sync;
+
+/// Handle to a ring's reactor, held by the I/O types built on it
+pub type Handle = Arc<DynReactor>;
+
+/// A pointer to something holding a driver, kept by op futures
+///
+/// `pub`, like the driver types it names, because it bounds the public I/O
+/// types; this module is private, so none of them can be named outside the
+/// crate.
+pub trait DriverRef: Clone + Unpin {
+    type Cell: CoreCell;
+
+    fn driver(&self) -> &Driver<Self::Cell>;
+}
+
+impl<S: AsRef<Driver> + ?Sized> DriverRef for Arc<S> {
+    type Cell = SharedCore;
+
+    fn driver(&self) -> &Driver {
+        (**self).as_ref()
+    }
+}
+
+impl DriverRef for Rc<LocalDriver> {
+    type Cell = RefCell<Core>;
+
+    fn driver(&self) -> &LocalDriver {
+        self
+    }
+}
+
+/// A deadline that the ops submitted under it are linked to
+#[derive(Clone)]
+pub(crate) struct Deadline {
+    at: Instant,
+    // Set once a linked timeout has cancelled one of the ops
+    expired: Arc<AtomicBool>,
+}
+
+impl Deadline {
+    pub(crate) fn new(at: Instant) -> Self {
+        Self {
+            at,
+            expired: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Run `f`, linking the ops first polled in it to an
+    /// `IORING_OP_LINK_TIMEOUT` at this deadline
+    ///
+    /// Within an earlier deadline, the earlier one is kept.
+    pub(crate) fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<Deadline>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let prev = self.0.take();
+                DEADLINE.with(|current| *current.borrow_mut() = prev);
+            }
+        }
+
+        let prev = DEADLINE.with(|current| {
+            let mut current = current.borrow_mut();
+            let prev = current.clone();
+            if prev.as_ref().map_or(true, |prev| self.at < prev.at) {
+                *current = Some(self.clone());
+            }
+            prev
+        });
+        let _restore = Restore(prev);
+        f()
+    }
+
+    /// Whether the kernel cancelled an op because the deadline passed
+    pub(crate) fn expired(&self) -> bool {
+        self.expired.load(Ordering::Acquire)
+    }
+
+    fn current() -> Option<Deadline> {
+        DEADLINE.with(|current| current.borrow().clone())
+    }
+}
+
+/// Future of a single operation, resolving with its first CQE and the op
+pub(crate) struct OpFuture<H: DriverRef, Op: Send + 'static> {
+    shared: H,
+    // Boxed so the SQE may point into it; `None` once resolved
+    op: Option<Box<Op>>,
+    // Boxed for the same reason, and linked to the op's SQE
+    link_timeout: Option<Box<LinkTimeout>>,
+    // The deadline `link_timeout` comes from, if a `time::timeout` set it
+    deadline: Option<Deadline>,
+    // The fd the op works on, kept open for as long as the op
+    fd: Option<Arc<OwnedFd>>,
+    // Takes the CQEs that arrive after the future is gone
+    on_late_cqe: Option<fn(Completion)>,
+    // Set while the op is in flight
+    user_data: Option<u64>,
+}
+
+impl<H: DriverRef, Op: Send + 'static> OpFuture<H, Op> {
+    pub(crate) fn new(shared: H, op: Op) -> Self {
+        Self {
+            shared,
+            op: Some(Box::new(op)),
+            link_timeout: None,
+            deadline: None,
+            fd: None,
+            on_late_cqe: None,
+            user_data: None,
+        }
+    }
+
+    /// An op the kernel cancels if it takes longer than `timeout`
+    pub(crate) fn with_timeout(shared: H, op: Op, timeout: Duration) -> Self {
+        let mut future = Self::new(shared, op);
+        future.link_timeout = Some(Box::new(LinkTimeout::new(timeout)));
+        future
+    }
+
+    /// Keep `fd` open until the kernel is done with the op, even if the
+    /// future is dropped first
+    pub(crate) fn keep_open(mut self, fd: Arc<OwnedFd>) -> Self {
+        self.fd = Some(fd);
+        self
+    }
+
+    /// Pass CQEs nobody will take to `hook`, for example to close the fd
+    /// an op created after its future was dropped
+    pub(crate) fn on_late_cqe(mut self, hook: fn(Completion)) -> Self {
+        self.on_late_cqe = Some(hook);
+        self
+    }
+}
+
+// The op lives in its own allocation, so the future itself can move
+impl<H: DriverRef, Op: Send + 'static> Unpin for OpFuture<H, Op> {}
+
+impl<H: DriverRef, Op: PrepareOp + Send + 'static> Future for OpFuture<H, Op> {
+    type Output = Result<(Completion, Op)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let driver = this.shared.driver();
+        let Some(op) = &this.op else {
+            return Poll::Ready(Err(Error::InvalidOperation(
+                "operation polled after completion".to_string(),
+            )));
+        };
+
+        let Some(user_data) = this.user_data else {
+            if this.link_timeout.is_none() {
+                if let Some(deadline) = Deadline::current() {
+                    this.link_timeout = Some(Box::new(LinkTimeout::at(deadline.at)));
+                    this.deadline = Some(deadline);
+                }
+            }
+            return match driver.push(&**op, this.link_timeout.as_deref(), cx.waker(), false) {
+                Ok(user_data) => {
+                    this.user_data = Some(user_data);
+                    Poll::Pending
+                }
+                Err(e) => {
+                    this.op = None;
+                    Poll::Ready(Err(e))
+                }
+            };
+        };
+
+        let completion = match ready!(driver.poll_slot(user_data, cx)) {
+            Ok(completion) => completion,
+            Err(e) => {
+                // The op may still be in flight; leave it to the driver
+                this.orphan();
+                return Poll::Ready(Err(e));
+            }
+        };
+        this.user_data = None;
+        if let Some(deadline) = &this.deadline {
+            if completion.result() == -libc::ECANCELED && Instant::now() >= deadline.at {
+                deadline.expired.store(true, Ordering::Release);
+            }
+        }
+        let op = this.op.take().unwrap();
+        Poll::Ready(Ok((completion, *op)))
+    }
+}
+
+impl<H: DriverRef, Op: Send + 'static> OpFuture<H, Op> {
+    /// Hand a submitted op over to the driver, which cancels it and frees
+    /// it once the kernel is done with it
+    fn orphan(&mut self) {
+        if let (Some(user_data), Some(op)) = (self.user_data.take(), self.op.take()) {
+            // The timeout may not have been submitted yet either
+            let op: Box<dyn Any + Send> = match self.link_timeout.take() {
+                Some(timeout) => Box::new((op, timeout)),
+                None => op,
+            };
+            let op = match self.fd.take() {
+                Some(fd) => Box::new((op, fd)),
+                None => op,
+            };
+            self.shared.driver().cancel(user_data, op, self.on_late_cqe);
+        }
+    }
+}
+
+impl<H: DriverRef, Op: Send + 'static> Drop for OpFuture<H, Op> {
+    fn drop(&mut self) {
+        self.orphan();
+    }
+}
+
+/// Where a multishot op is in its lifecycle
+#[derive(Clone, Copy)]
+enum StreamState {
+    Idle,
+    InFlight(u64),
+    Done,
+}
+
+/// Stream of the CQEs of a multishot operation
+///
+/// Ends after the CQE without `IORING_CQE_F_MORE`. The stream keeps the op,
+/// so it can be submitted again with [`rearm`](Self::rearm).
+pub(crate) struct OpStream<H: DriverRef, Op: Send + 'static> {
+    shared: H,
+    // Boxed so the SQE may point into it; `None` once handed to the driver
+    op: Option<Box<Op>>,
+    state: StreamState,
+    // As in `OpFuture`
+    fd: Option<Arc<OwnedFd>>,
+    on_late_cqe: Option<fn(Completion)>,
+}
+
+impl<H: DriverRef, Op: Send + 'static> OpStream<H, Op> {
+    pub(crate) fn new(shared: H, op: Op) -> Self {
+        Self {
+            shared,
+            op: Some(Box::new(op)),
+            state: StreamState::Idle,
+            fd: None,
+            on_late_cqe: None,
+        }
+    }
+
+    /// See [`OpFuture::keep_open`]
+    pub(crate) fn keep_open(mut self, fd: Arc<OwnedFd>) -> Self {
+        self.fd = Some(fd);
+        self
+    }
+
+    /// See [`OpFuture::on_late_cqe`]; this includes CQEs that arrived but
+    /// were not taken before the stream was dropped
+    pub(crate) fn on_late_cqe(mut self, hook: fn(Completion)) -> Self {
+        self.on_late_cqe = Some(hook);
+        self
+    }
+
+    /// Hand the op over to the driver, like `OpFuture` does when dropped
+    fn orphan(&mut self, user_data: u64) {
+        if let Some(op) = self.op.take() {
+            let op: Box<dyn Any + Send> = match self.fd.take() {
+                Some(fd) => Box::new((op, fd)),
+                None => op,
+            };
+            self.shared.driver().cancel(user_data, op, self.on_late_cqe);
+        }
+    }
+
+    /// Whether the op has posted its final CQE
+    pub(crate) fn is_done(&self) -> bool {
+        matches!(self.state, StreamState::Done)
+    }
+
+    /// Submit the op again on the next poll, once it has finished
+    pub(crate) fn rearm(&mut self) {
+        if self.is_done() && self.op.is_some() {
+            self.state = StreamState::Idle;
+        }
+    }
+}
+
+impl<H: DriverRef, Op: Send + 'static> Unpin for OpStream<H, Op> {}
+
+impl<H: DriverRef, Op: PrepareOp + Send + 'static> futures::Stream for OpStream<H, Op> {
+    type Item = Result<Completion>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let driver = this.shared.driver();
+        let Some(op) = this.op.as_deref() else {
+            return Poll::Ready(None);
+        };
+
+        let res = match this.state {
+            StreamState::Done => return Poll::Ready(None),
+            StreamState::Idle => match driver.push(op, None, cx.waker(), true) {
+                Ok(user_data) => {
+                    this.state = StreamState::InFlight(user_data);
+                    return Poll::Pending;
+                }
+                Err(e) => Err(e),
+            },
+            StreamState::InFlight(user_data) => {
+                let res = ready!(driver.poll_stream(user_data, cx));
+                if res.is_err() {
+                    // The op may still be in flight; leave it to the driver
+                    this.orphan(user_data);
+                }
+                res
+            }
+        };
+
+        if !res.as_ref().is_ok_and(Completion::has_more) {
+            this.state = StreamState::Done;
+        }
+        Poll::Ready(Some(res))
+    }
+}
+
+impl<H: DriverRef, Op: Send + 'static> Drop for OpStream<H, Op> {
+    fn drop(&mut self) {
+        if let StreamState::InFlight(user_data) = self.state {
+            self.orphan(user_data);
+        }
+    }
+}
+
+/// Map the errors that only ask for a retry to `Ok(false)`
+fn retry_later(res: Result<()>) -> Result<bool> {
+    match res {
+        Ok(()) => Ok(true),
+        // The CQ is full, the kernel is short on memory, or a signal
+        // interrupted the wait; reap and retry
+        Err(Error::Io(e))
+            if matches!(
+                e.raw_os_error(),
+                Some(libc::EBUSY | libc::EAGAIN | libc::EINTR)
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn register(waker: &mut Option<Waker>, cx: &Context<'_>) {
+    match waker {
+        Some(waker) if waker.will_wake(cx.waker()) => {}
+        _ => *waker = Some(cx.waker().clone()),
+    }
+}
+
+fn no_slot(user_data: u64) -> Error {
+    Error::InvalidOperation(format!(
+        "no in-flight operation with user_data {}",
+        user_data
+    ))
+}
+
+fn stopped(reason: &str) -> Error {
+    Error::InvalidOperation(format!("io_uring driver stopped: {}", reason))
+}
@@ -0,0 +1,575 @@
+//! Async file I/O on top of an async ring
+//!
+//! [`File`] reads and writes through the [`Ring`] it was opened with.
+//! Positional I/O takes owned buffers, so nothing borrowed is left with the
+//! kernel if a future is dropped. The stream traits copy through a buffer
+//! owned by the file.
+
+use super::buf::{Buf, ReadBuf, WriteBuf, CURRENT_POSITION};
+use super::driver::{DriverRef, OpFuture};
+use super::{handle, Ring, SharedHandle};
+use crate::{
+    ops::{Fsync, PrepareOp},
+    sys, Error, Result,
+};
+use std::cell::UnsafeCell;
+use std::ffi::CString;
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::io::{self, SeekFrom};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Largest chunk moved by a single read or write through the stream traits
+const MAX_BUF: usize = 2 * 1024 * 1024;
+
+/// A file whose I/O is submitted to an io_uring
+///
+/// Reads and writes through the `AsyncRead` and `AsyncWrite` traits, both
+/// tokio's and those of `futures-io`, use and advance the file position
+/// like `std::fs::File` does. A write is copied into the file's buffer and
+/// submitted right away; call `flush` to wait for it and see its result.
+/// Dropping the file cancels a write that is still in flight; the fd is
+/// closed once the kernel is done with every op on it.
+///
+/// `H` is the handle of the ring the file was opened on, see [`Ring`].
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use liburing_rs::async_io::{fs::File, AsyncIoUring};
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+///
+/// let ring = AsyncIoUring::new(32)?;
+///
+/// let mut file = File::create(&ring, "/tmp/hello.txt").await?;
+/// file.write_all(b"hello").await?;
+/// file.flush().await?;
+///
+/// let mut contents = String::new();
+/// File::open(&ring, "/tmp/hello.txt")
+///     .await?
+///     .read_to_string(&mut contents)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct File<H: DriverRef = SharedHandle> {
+    handle: H,
+    // Shared with every op on it, so it stays open until they are done
+    fd: Arc<OwnedFd>,
+    state: State<H>,
+    // Target of a tokio `start_seek` until `poll_complete` is done with it
+    #[cfg(feature = "async-tokio")]
+    seek: Option<SeekFrom>,
+}
+
+/// What the stream traits are doing
+enum State<H: DriverRef> {
+    /// No op in flight
+    Idle(Buf),
+    Reading(OpFuture<H, ReadBuf>),
+    Writing(OpFuture<H, WriteBuf>),
+}
+
+impl<H: DriverRef> File<H> {
+    /// Open a file in read-only mode
+    pub async fn open(ring: &impl Ring<Handle = H>, path: impl AsRef<Path>) -> Result<File<H>> {
+        Self::open_with(ring, path.as_ref(), libc::O_RDONLY, 0).await
+    }
+
+    /// Open a file in write-only mode, creating it or truncating it
+    pub async fn create(ring: &impl Ring<Handle = H>, path: impl AsRef<Path>) -> Result<File<H>> {
+        let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC;
+        Self::open_with(ring, path.as_ref(), flags, 0o666).await
+    }
+
+    async fn open_with(
+        ring: &impl Ring<Handle = H>,
+        path: &Path,
+        flags: i32,
+        mode: u32,
+    ) -> Result<File<H>> {
+        let path =
+            CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::InvalidParameter {
+                field: "path",
+                reason: "path contains a nul byte".to_string(),
+            })?;
+
+        let handle = handle(ring);
+        let open = OpenAt {
+            path,
+            flags: flags | libc::O_CLOEXEC,
+            mode,
+        };
+        let (completion, _) = OpFuture::new(handle.clone(), open).await?;
+        let fd = completion.into_result()?;
+        Ok(Self::from_parts(handle, unsafe {
+            OwnedFd::from_raw_fd(fd)
+        }))
+    }
+
+    /// Wrap an already opened file
+    pub fn from_std(ring: &impl Ring<Handle = H>, file: std::fs::File) -> File<H> {
+        Self::from_parts(handle(ring), file.into())
+    }
+
+    fn from_parts(handle: H, fd: OwnedFd) -> File<H> {
+        File {
+            handle,
+            fd: Arc::new(fd),
+            state: State::Idle(Buf::default()),
+            #[cfg(feature = "async-tokio")]
+            seek: None,
+        }
+    }
+
+    /// Read at `offset` into the spare capacity of `buf`
+    ///
+    /// The bytes read are appended to `buf`, which is handed back along
+    /// with their count. The file position is not used or changed.
+    pub async fn read_at(&self, buf: Vec<u8>, offset: u64) -> Result<(usize, Vec<u8>)> {
+        let len = buf.capacity() - buf.len();
+        let (completion, op) = self
+            .op(ReadBuf::new(self.raw_fd(), buf, len, offset))
+            .await?;
+        let (res, buf) = op.finish(completion);
+        Ok((res?, buf))
+    }
+
+    /// Write `buf` at `offset`
+    ///
+    /// Returns the number of bytes written, which may be short, and the
+    /// buffer. The file position is not used or changed.
+    pub async fn write_at(&self, buf: Vec<u8>, offset: u64) -> Result<(usize, Vec<u8>)> {
+        let (completion, mut op) = self.op(WriteBuf::new(self.raw_fd(), buf, offset)).await?;
+        let n = op.advance(completion)?;
+        Ok((n, op.into_buf()))
+    }
+
+    /// Flush written data and metadata to disk
+    ///
+    /// Writes made through the stream traits are waited for first.
+    pub async fn sync_all(&mut self) -> Result<()> {
+        self.sync(Fsync::new(self.raw_fd())).await
+    }
+
+    /// Flush written data to disk, skipping metadata not needed to read it
+    ///
+    /// Writes made through the stream traits are waited for first.
+    pub async fn sync_data(&mut self) -> Result<()> {
+        self.sync(Fsync::data_sync(self.raw_fd())).await
+    }
+
+    async fn sync(&mut self, fsync: Fsync) -> Result<()> {
+        poll_fn(|cx| self.poll_idle(cx)).await?;
+        let (completion, _) = self.op(fsync).await?;
+        completion.into_result()?;
+        Ok(())
+    }
+
+    /// Truncate or extend the file to `size` bytes
+    ///
+    /// Writes made through the stream traits are waited for first. The
+    /// file position is left alone. Needs Linux 6.9.
+    pub async fn set_len(&mut self, size: u64) -> Result<()> {
+        poll_fn(|cx| self.poll_idle(cx)).await?;
+        let truncate = Ftruncate {
+            fd: self.raw_fd(),
+            len: size,
+        };
+        let (completion, _) = self.op(truncate).await?;
+        completion.into_result()?;
+        Ok(())
+    }
+
+    /// Query the file's metadata with `statx`
+    pub async fn metadata(&self) -> Result<Metadata> {
+        let statx = Statx {
+            fd: self.raw_fd(),
+            buf: UnsafeCell::new(unsafe { std::mem::zeroed() }),
+        };
+        let (completion, statx) = self.op(statx).await?;
+        completion.into_result()?;
+        Ok(Metadata {
+            statx: statx.buf.into_inner(),
+        })
+    }
+
+    fn op<Op: PrepareOp + Send + 'static>(&self, op: Op) -> OpFuture<H, Op> {
+        OpFuture::new(self.handle.clone(), op).keep_open(self.fd.clone())
+    }
+
+    fn raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Finish whatever op the stream traits have in flight
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match &mut self.state {
+                State::Idle(_) => return Poll::Ready(Ok(())),
+                State::Reading(op) => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    self.state = State::Idle(Buf::default());
+
+                    let (completion, op) = res?;
+                    let (res, data) = op.finish(completion);
+                    self.state = State::Idle(Buf::new(data));
+                    res?;
+                }
+                State::Writing(op) => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    self.state = State::Idle(Buf::default());
+
+                    let (completion, mut op) = res?;
+                    op.advance(completion)?;
+                    if op.is_done() {
+                        let mut data = op.into_buf();
+                        data.clear();
+                        self.state = State::Idle(Buf::new(data));
+                    } else {
+                        // Short write, carry on with the rest
+                        self.state = State::Writing(self.op(op));
+                    }
+                }
+            }
+        }
+    }
+
+    fn poll_read_into(&mut self, cx: &mut Context<'_>, dst: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            match &mut self.state {
+                State::Idle(buf) => {
+                    if buf.unread() > 0 || dst.is_empty() {
+                        return Poll::Ready(Ok(buf.copy_to(dst)));
+                    }
+                    let data = buf.take();
+                    let len = dst.len().min(MAX_BUF);
+                    let read = ReadBuf::new(self.raw_fd(), data, len, CURRENT_POSITION);
+                    self.state = State::Reading(self.op(read));
+                }
+                State::Reading(_) => {
+                    ready!(self.poll_idle(cx))?;
+                    // Whatever came back, including nothing at end of file
+                    let State::Idle(buf) = &mut self.state else {
+                        unreachable!()
+                    };
+                    return Poll::Ready(Ok(buf.copy_to(dst)));
+                }
+                State::Writing(_) => ready!(self.poll_idle(cx))?,
+            }
+        }
+    }
+
+    fn poll_write_from(&mut self, cx: &mut Context<'_>, src: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.poll_idle(cx))?;
+        self.discard_read_ahead()?;
+        if src.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let State::Idle(buf) = &mut self.state else {
+            unreachable!()
+        };
+        let mut data = buf.take();
+        let n = src.len().min(MAX_BUF);
+        data.extend_from_slice(&src[..n]);
+        let write = WriteBuf::new(self.raw_fd(), data, CURRENT_POSITION);
+        self.state = State::Writing(self.op(write));
+
+        // Queue the SQE now; the result is picked up by the next write,
+        // flush or seek
+        if let Poll::Ready(Err(e)) = self.poll_idle(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush_writes(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if matches!(self.state, State::Writing(_)) {
+            ready!(self.poll_idle(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_seek_to(&mut self, cx: &mut Context<'_>, pos: SeekFrom) -> Poll<io::Result<u64>> {
+        ready!(self.poll_idle(cx))?;
+        let read_ahead = self.discard_read_ahead_len();
+
+        // The kernel's position is past the data read ahead
+        let (offset, whence) = match pos {
+            SeekFrom::Start(n) => (n as i64, libc::SEEK_SET),
+            SeekFrom::End(n) => (n, libc::SEEK_END),
+            SeekFrom::Current(n) => (n - read_ahead as i64, libc::SEEK_CUR),
+        };
+        Poll::Ready(self.lseek(offset, whence))
+    }
+
+    /// Move the file position back over data read ahead but not returned
+    fn discard_read_ahead(&mut self) -> io::Result<()> {
+        let read_ahead = self.discard_read_ahead_len();
+        if read_ahead > 0 {
+            self.lseek(-(read_ahead as i64), libc::SEEK_CUR)?;
+        }
+        Ok(())
+    }
+
+    fn discard_read_ahead_len(&mut self) -> usize {
+        match &mut self.state {
+            State::Idle(buf) => {
+                let read_ahead = buf.unread();
+                buf.clear();
+                read_ahead
+            }
+            _ => 0,
+        }
+    }
+
+    fn lseek(&self, offset: i64, whence: i32) -> io::Result<u64> {
+        let ret = unsafe { libc::lseek(self.raw_fd(), offset as libc::off_t, whence) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ret as u64)
+    }
+}
+
+impl<H: DriverRef> AsRawFd for File<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl<H: DriverRef> AsFd for File<H> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+impl<H: DriverRef> tokio::io::AsyncRead for File<H> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = ready!(self.get_mut().poll_read_into(cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+impl<H: DriverRef> tokio::io::AsyncWrite for File<H> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_from(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_writes(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_writes(cx)
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+impl<H: DriverRef> tokio::io::AsyncSeek for File<H> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        if this.seek.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "other file operation is pending, call poll_complete before start_seek",
+            ));
+        }
+        this.seek = Some(position);
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let pos = this.seek.unwrap_or(SeekFrom::Current(0));
+        let res = ready!(this.poll_seek_to(cx, pos));
+        this.seek = None;
+        Poll::Ready(res)
+    }
+}
+
+impl<H: DriverRef> futures::io::AsyncRead for File<H> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_into(cx, buf)
+    }
+}
+
+impl<H: DriverRef> futures::io::AsyncWrite for File<H> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_from(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_writes(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_writes(cx)
+    }
+}
+
+impl<H: DriverRef> futures::io::AsyncSeek for File<H> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        self.get_mut().poll_seek_to(cx, pos)
+    }
+}
+
+/// Metadata of a file, as reported by `statx(2)`
+#[derive(Clone)]
+pub struct Metadata {
+    statx: libc::statx,
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl Metadata {
+    /// Size of the file in bytes
+    pub fn len(&self) -> u64 {
+        self.statx.stx_size
+    }
+
+    /// Whether this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == libc::S_IFDIR
+    }
+
+    /// Whether this is a regular file
+    pub fn is_file(&self) -> bool {
+        self.file_type() == libc::S_IFREG
+    }
+
+    /// Whether this is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.file_type() == libc::S_IFLNK
+    }
+
+    /// File type and permission bits, as in `st_mode`
+    pub fn mode(&self) -> u32 {
+        self.statx.stx_mode as u32
+    }
+
+    /// Time of the last modification
+    pub fn modified(&self) -> SystemTime {
+        system_time(&self.statx.stx_mtime)
+    }
+
+    /// Time of the last access
+    pub fn accessed(&self) -> SystemTime {
+        system_time(&self.statx.stx_atime)
+    }
+
+    /// Time of creation, if the filesystem records it
+    pub fn created(&self) -> Option<SystemTime> {
+        (self.statx.stx_mask & libc::STATX_BTIME != 0).then(|| system_time(&self.statx.stx_btime))
+    }
+
+    fn file_type(&self) -> u32 {
+        self.mode() & libc::S_IFMT
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("len", &self.len())
+            .field("mode", &format_args!("{:#o}", self.mode()))
+            .field("modified", &self.modified())
+            .finish_non_exhaustive()
+    }
+}
+
+fn system_time(ts: &libc::statx_timestamp) -> SystemTime {
+    let nanos = Duration::from_nanos(ts.tv_nsec.into());
+    if ts.tv_sec >= 0 {
+        UNIX_EPOCH + Duration::from_secs(ts.tv_sec as u64) + nanos
+    } else {
+        UNIX_EPOCH - Duration::from_secs(ts.tv_sec.unsigned_abs()) + nanos
+    }
+}
+
+/// `openat` relative to the working directory, owning the path
+struct OpenAt {
+    path: CString,
+    flags: i32,
+    mode: u32,
+}
+
+impl PrepareOp for OpenAt {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_openat(
+                sqe,
+                libc::AT_FDCWD,
+                self.path.as_ptr(),
+                self.flags,
+                self.mode,
+            );
+        }
+    }
+}
+
+/// `statx` of an open file, owning the result buffer
+struct Statx {
+    fd: RawFd,
+    buf: UnsafeCell<libc::statx>,
+}
+
+impl PrepareOp for Statx {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_statx(
+                sqe,
+                self.fd,
+                b"\0".as_ptr() as *const _,
+                libc::AT_EMPTY_PATH,
+                libc::STATX_BASIC_STATS | libc::STATX_BTIME,
+                self.buf.get(),
+            );
+        }
+    }
+}
+
+struct Ftruncate {
+    fd: RawFd,
+    len: u64,
+}
+
+impl PrepareOp for Ftruncate {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_ftruncate(sqe, self.fd, self.len as i64);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Runtime-independent io_uring driver
+//!
+//! Nothing here spawns tasks or registers with a runtime's reactor. The
+//! ring makes progress whenever its owner calls [`AsyncIoUring::drive`] or
+//! [`AsyncIoUring::poll_completions`], which suits custom executors and
+//! runtimes without a backend of their own.
+
+use super::driver::{self, Driver, OpFuture};
+use super::multishot::Multishot;
+use crate::{ops::PrepareOp, Result};
+use std::future::Future;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Async io_uring instance driven by its owner
+///
+/// Operations are submitted and their futures woken the same way as with
+/// the runtime backends, but only while someone drives the ring:
+///
+/// * [`drive`](Self::drive) blocks until at least one in-flight op
+///   completes, which is what a single-threaded executor calls when it has
+///   no task left to run.
+/// * [`poll_completions`](Self::poll_completions) never blocks. Call it
+///   from a task or executor loop whenever the fd from [`as_raw_fd`]
+///   becomes readable or its waker fires.
+///
+/// Handles are cheap to clone and may be shared between threads.
+///
+/// [`as_raw_fd`]: AsRawFd::as_raw_fd
+///
+/// # Example
+///
+/// ```no_run
+/// use futures::task::noop_waker;
+/// use liburing_rs::async_io::generic_impl::AsyncIoUring;
+/// use liburing_rs::ops::Nop;
+/// use std::future::Future;
+/// use std::task::{Context, Poll};
+///
+/// # fn main() -> liburing_rs::Result<()> {
+/// let ring = AsyncIoUring::new(32)?;
+/// let mut nop = Box::pin(ring.submit_op(Nop));
+///
+/// let waker = noop_waker();
+/// let mut cx = Context::from_waker(&waker);
+/// let result = loop {
+///     if let Poll::Ready(result) = nop.as_mut().poll(&mut cx) {
+///         break result?;
+///     }
+///     ring.drive()?;
+/// };
+/// println!("NOP completed with result: {}", result);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncIoUring {
+    driver: Arc<Driver>,
+}
+
+impl AsyncIoUring {
+    /// Create a new async io_uring instance with the specified number of entries
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Number of submission queue entries (will be rounded up to power of 2)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kernel doesn't support io_uring or if setup fails.
+    pub fn new(entries: u32) -> Result<Self> {
+        Self::setup(entries, false)
+    }
+
+    /// Create a new async io_uring instance that signals completions through
+    /// an eventfd
+    ///
+    /// The eventfd is registered with the ring and returned by `as_raw_fd`
+    /// in place of the ring fd.
+    pub fn with_eventfd(entries: u32) -> Result<Self> {
+        Self::setup(entries, true)
+    }
+
+    fn setup(entries: u32, use_eventfd: bool) -> Result<Self> {
+        let (ring, eventfd) = driver::setup_ring(entries, use_eventfd)?;
+        Ok(Self {
+            driver: Arc::new(Driver::new(ring, eventfd)),
+        })
+    }
+
+    /// Submit queued SQEs and block until at least one op completes
+    ///
+    /// The futures of completed ops are woken. Returns right away when no
+    /// op is in flight. The ring isn't locked while it blocks: other
+    /// threads can queue ops and reap completions meanwhile, which makes
+    /// `drive` return early so its caller can poll again. Only one thread
+    /// blocks at a time; a `drive` called meanwhile waits for it to return.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that made submitting fail. The ring is unusable
+    /// afterwards, and every pending op fails with an error too.
+    pub fn drive(&self) -> Result<()> {
+        self.driver.drive()
+    }
+
+    /// Submit queued SQEs and dispatch the completions already posted
+    ///
+    /// Stays pending for as long as the ring works; `cx` is woken when new
+    /// SQEs are queued. Completions are not signalled through `cx`: poll
+    /// again once the fd from `as_raw_fd` is readable.
+    ///
+    /// # Errors
+    ///
+    /// Resolves with the error that made submitting fail, after which the
+    /// ring is unusable.
+    pub fn poll_completions(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.driver.poll_completions(cx)
+    }
+
+    /// Submit an operation and wait for its completion asynchronously
+    ///
+    /// The SQE is queued on the first poll and submitted by the next
+    /// `drive` or `poll_completions`.
+    ///
+    /// The op is owned by the future until its CQE arrives, so any buffer
+    /// it points into stays valid. If the future is dropped while the op is
+    /// in flight, the op is cancelled with `IORING_OP_ASYNC_CANCEL` and kept
+    /// alive by the driver until the kernel posts its final completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The operation to submit (implements `PrepareOp`)
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to the result code of the operation
+    pub fn submit_op<Op: PrepareOp + Send + 'static>(
+        &self,
+        op: Op,
+    ) -> impl Future<Output = Result<i32>> {
+        let op = OpFuture::new(self.driver.clone(), op);
+        async move { op.await.map(|(completion, _)| completion.result()) }
+    }
+
+    /// Submit a multishot operation and stream its completions
+    ///
+    /// Every CQE the op posts is yielded in order; the stream ends with the
+    /// one without `IORING_CQE_F_MORE`. Call [`Multishot::rearm`] on the
+    /// result to keep the op armed instead. Dropping the stream cancels the
+    /// op.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The multishot operation to submit (implements `PrepareOp`)
+    pub fn submit_multishot<Op: PrepareOp + Send + 'static>(&self, op: Op) -> Multishot<Op> {
+        Multishot::new(self.driver.clone(), op)
+    }
+}
+
+impl AsRawFd for AsyncIoUring {
+    /// The fd that becomes readable when completions arrive: the eventfd
+    /// if there is one, else the ring fd
+    fn as_raw_fd(&self) -> RawFd {
+        self.driver.readiness_fd().as_raw_fd()
+    }
+}
+
+impl super::sealed::Sealed for AsyncIoUring {
+    type Handle = super::SharedHandle;
+
+    fn ring_handle(&self) -> super::SharedHandle {
+        super::SharedHandle(self.driver.clone())
+    }
+}
+
+impl super::Ring for AsyncIoUring {}
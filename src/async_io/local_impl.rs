@@ -0,0 +1,184 @@
+//! Single-threaded io_uring driver
+//!
+//! The thread-per-ring counterpart of [`generic_impl`](super::generic_impl).
+//! The ring is set up with `IORING_SETUP_SINGLE_ISSUER` and
+//! `IORING_SETUP_DEFER_TASKRUN`, and its state sits in a `RefCell` rather
+//! than behind a lock, since it never leaves the thread that created it.
+
+use super::driver::{self, LocalDriver, OpFuture};
+use super::multishot::LocalMultishot;
+use crate::{ops::PrepareOp, IoUring, Result};
+use std::future::Future;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// Async io_uring instance owned by a single thread
+///
+/// Works like [`generic_impl::AsyncIoUring`](super::generic_impl::AsyncIoUring),
+/// driven by [`drive`](Self::drive) or
+/// [`poll_completions`](Self::poll_completions), without the lock around
+/// the ring. Handles are cheap to clone but are neither `Send` nor `Sync`.
+///
+/// With `DEFER_TASKRUN` the kernel finishes completions only when the ring
+/// is reaped: `drive` does so while it waits, and `poll_completions` asks
+/// for the deferred work with `io_uring_get_events` before dispatching.
+/// The fd from [`as_raw_fd`](AsRawFd::as_raw_fd) still becomes readable
+/// when there is work to run.
+///
+/// The ring fd is registered with the ring, so `io_uring_enter` skips the
+/// fd lookup; the registration only holds on this thread, as the ring does.
+///
+/// Requires Linux 6.1 or later. Files, sockets and timers from
+/// [`fs`](super::fs), [`net`](super::net) and [`time`](super::time) can be
+/// opened on it too, and stay on its thread like the ring.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures::task::noop_waker;
+/// use liburing_rs::async_io::local_impl::AsyncIoUring;
+/// use liburing_rs::ops::Nop;
+/// use std::future::Future;
+/// use std::task::{Context, Poll};
+///
+/// # fn main() -> liburing_rs::Result<()> {
+/// let ring = AsyncIoUring::new(32)?;
+/// let mut nop = Box::pin(ring.submit_op(Nop));
+///
+/// let waker = noop_waker();
+/// let mut cx = Context::from_waker(&waker);
+/// let result = loop {
+///     if let Poll::Ready(result) = nop.as_mut().poll(&mut cx) {
+///         break result?;
+///     }
+///     ring.drive()?;
+/// };
+/// println!("NOP completed with result: {}", result);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncIoUring {
+    driver: Rc<LocalDriver>,
+}
+
+impl AsyncIoUring {
+    /// Create a new async io_uring instance with the specified number of entries
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Number of submission queue entries (will be rounded up to power of 2)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kernel doesn't support io_uring, or lacks
+    /// `SINGLE_ISSUER` or `DEFER_TASKRUN`.
+    pub fn new(entries: u32) -> Result<Self> {
+        Self::setup(entries, false)
+    }
+
+    /// Create a new async io_uring instance that signals completions through
+    /// an eventfd
+    ///
+    /// The eventfd is registered with the ring and returned by `as_raw_fd`
+    /// in place of the ring fd.
+    pub fn with_eventfd(entries: u32) -> Result<Self> {
+        Self::setup(entries, true)
+    }
+
+    fn setup(entries: u32, use_eventfd: bool) -> Result<Self> {
+        let builder = IoUring::builder().single_issuer().defer_taskrun();
+        let (mut ring, eventfd) = driver::setup_ring_with(builder, entries, use_eventfd)?;
+        // Without free slots for it the plain fd does just as well
+        let _ = ring.register_ring_fd();
+        Ok(Self {
+            driver: Rc::new(LocalDriver::new(ring, eventfd)),
+        })
+    }
+
+    /// Submit queued SQEs and block until at least one op completes
+    ///
+    /// The futures of completed ops are woken. Returns right away when no
+    /// op is in flight.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that made submitting fail. The ring is unusable
+    /// afterwards, and every pending op fails with an error too.
+    pub fn drive(&self) -> Result<()> {
+        self.driver.drive()
+    }
+
+    /// Submit queued SQEs, run deferred task work and dispatch the
+    /// completions it posted
+    ///
+    /// Stays pending for as long as the ring works; `cx` is woken when new
+    /// SQEs are queued. Completions are not signalled through `cx`: poll
+    /// again once the fd from `as_raw_fd` is readable.
+    ///
+    /// # Errors
+    ///
+    /// Resolves with the error that made submitting fail, after which the
+    /// ring is unusable.
+    pub fn poll_completions(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.driver.poll_completions(cx)
+    }
+
+    /// Submit an operation and wait for its completion asynchronously
+    ///
+    /// The SQE is queued on the first poll and submitted by the next
+    /// `drive` or `poll_completions`.
+    ///
+    /// The op is owned by the future until its CQE arrives, so any buffer
+    /// it points into stays valid. If the future is dropped while the op is
+    /// in flight, the op is cancelled with `IORING_OP_ASYNC_CANCEL` and kept
+    /// alive by the driver until the kernel posts its final completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The operation to submit (implements `PrepareOp`)
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to the result code of the operation
+    pub fn submit_op<Op: PrepareOp + Send + 'static>(
+        &self,
+        op: Op,
+    ) -> impl Future<Output = Result<i32>> {
+        let op = OpFuture::new(self.driver.clone(), op);
+        async move { op.await.map(|(completion, _)| completion.result()) }
+    }
+
+    /// Submit a multishot operation and stream its completions
+    ///
+    /// Every CQE the op posts is yielded in order; the stream ends with the
+    /// one without `IORING_CQE_F_MORE`. Call [`LocalMultishot::rearm`] on
+    /// the result to keep the op armed instead. Dropping the stream cancels
+    /// the op.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The multishot operation to submit (implements `PrepareOp`)
+    pub fn submit_multishot<Op: PrepareOp + Send + 'static>(&self, op: Op) -> LocalMultishot<Op> {
+        LocalMultishot::new(self.driver.clone(), op)
+    }
+}
+
+impl AsRawFd for AsyncIoUring {
+    /// The fd that becomes readable when completions arrive: the eventfd
+    /// if there is one, else the ring fd
+    fn as_raw_fd(&self) -> RawFd {
+        self.driver.readiness_fd().as_raw_fd()
+    }
+}
+
+impl super::sealed::Sealed for AsyncIoUring {
+    type Handle = super::LocalHandle;
+
+    fn ring_handle(&self) -> super::LocalHandle {
+        super::LocalHandle(self.driver.clone())
+    }
+}
+
+impl super::Ring for AsyncIoUring {}
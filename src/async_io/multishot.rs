@@ -0,0 +1,110 @@
+//! Streams of multishot completions
+
+use super::driver::{DriverRef, Handle, LocalDriver, OpStream};
+use crate::{ops::PrepareOp, Completion, Result};
+use futures::Stream;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll};
+
+/// Stream of every CQE posted by a multishot operation
+///
+/// Returned by `AsyncIoUring::submit_multishot`. The op is submitted on the
+/// first poll, and each CQE carrying its user_data is yielded as it
+/// arrives, errors included. The stream ends after the CQE without
+/// `IORING_CQE_F_MORE`, unless [`rearm`](Self::rearm) asked for the op to
+/// be submitted again.
+///
+/// Dropping the stream while the op is armed cancels it; the op is kept
+/// alive until the kernel has posted its final CQE.
+pub struct Multishot<Op: Send + 'static> {
+    stream: OpStream<Handle, Op>,
+    rearm: bool,
+}
+
+impl<Op: Send + 'static> Multishot<Op> {
+    pub(crate) fn new(handle: Handle, op: Op) -> Self {
+        Self {
+            stream: OpStream::new(handle, op),
+            rearm: false,
+        }
+    }
+
+    /// Submit the op again whenever the kernel ends it
+    ///
+    /// The kernel disarms a multishot op when it can't post more CQEs, for
+    /// example when the CQ ring overflows or it runs out of provided buffers.
+    /// With re-arming the stream only ends once the op can't be submitted or
+    /// fails with an error that would recur, such as `EINVAL` or `EBADF`;
+    /// that error is yielded first.
+    pub fn rearm(mut self) -> Self {
+        self.rearm = true;
+        self
+    }
+}
+
+impl<Op: PrepareOp + Send + 'static> Stream for Multishot<Op> {
+    type Item = Result<Completion>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        poll_rearming(&mut this.stream, this.rearm, cx)
+    }
+}
+
+/// [`Multishot`] of a ring that stays on one thread
+///
+/// Returned by `local_impl::AsyncIoUring::submit_multishot`; it behaves the
+/// same but is neither `Send` nor `Sync`.
+pub struct LocalMultishot<Op: Send + 'static> {
+    stream: OpStream<Rc<LocalDriver>, Op>,
+    rearm: bool,
+}
+
+impl<Op: Send + 'static> LocalMultishot<Op> {
+    pub(crate) fn new(driver: Rc<LocalDriver>, op: Op) -> Self {
+        Self {
+            stream: OpStream::new(driver, op),
+            rearm: false,
+        }
+    }
+
+    /// Submit the op again whenever the kernel ends it
+    ///
+    /// See [`Multishot::rearm`].
+    pub fn rearm(mut self) -> Self {
+        self.rearm = true;
+        self
+    }
+}
+
+impl<Op: PrepareOp + Send + 'static> Stream for LocalMultishot<Op> {
+    type Item = Result<Completion>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        poll_rearming(&mut this.stream, this.rearm, cx)
+    }
+}
+
+pub(super) fn poll_rearming<H: DriverRef, Op: PrepareOp + Send + 'static>(
+    stream: &mut OpStream<H, Op>,
+    rearm: bool,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Result<Completion>>> {
+    let item = ready!(Pin::new(&mut *stream).poll_next(cx));
+    // An `Err` comes from the driver and would just repeat
+    if rearm && matches!(&item, Some(Ok(completion)) if worth_rearming(completion)) {
+        stream.rearm();
+    }
+    Poll::Ready(item)
+}
+
+/// Whether the kernel ended a multishot op for a reason that may pass
+///
+/// Timeouts post `ETIME` when they fire; running short of provided buffers
+/// or memory is also temporary. Any other error would be posted again.
+fn worth_rearming(completion: &Completion) -> bool {
+    let res = completion.result();
+    res >= 0 || [libc::ETIME, libc::ENOBUFS, libc::ENOMEM].contains(&-res)
+}
@@ -0,0 +1,18 @@
+//! Async networking on top of [`AsyncIoUring`](super::AsyncIoUring)
+//!
+//! Sockets are created and bound with plain syscalls, which never block.
+//! Connecting, accepting and moving data go through the ring. Listeners
+//! offer a stream of connections backed by a multishot accept, and the
+//! connected streams implement the `AsyncRead` and `AsyncWrite` traits of
+//! tokio and of `futures-io`.
+
+mod ops;
+mod stream;
+mod tcp;
+mod udp;
+mod unix;
+
+pub use stream::{OwnedReadHalf, OwnedWriteHalf, ReadHalf, WriteHalf};
+pub use tcp::{Incoming, TcpListener, TcpStream};
+pub use udp::UdpSocket;
+pub use unix::{UnixIncoming, UnixListener, UnixStream};
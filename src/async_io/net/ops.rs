@@ -0,0 +1,283 @@
+//! Socket operations and addresses
+//!
+//! Like the buffer ops, each of these owns everything the kernel reads or
+//! writes while it is in flight.
+
+use crate::{ops::PrepareOp, sys};
+use std::cell::UnsafeCell;
+use std::io;
+use std::mem;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+
+/// A socket address in its raw form
+#[derive(Clone, Copy)]
+pub(super) struct SockAddr {
+    storage: libc::sockaddr_storage,
+    len: libc::socklen_t,
+}
+
+impl SockAddr {
+    /// Room for any address, to be filled in by the kernel
+    pub(super) fn empty() -> Self {
+        Self {
+            storage: unsafe { mem::zeroed() },
+            len: mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t,
+        }
+    }
+
+    pub(super) fn inet(addr: &SocketAddr) -> Self {
+        let mut raw = Self::empty();
+        match addr {
+            SocketAddr::V4(addr) => {
+                let sin = unsafe { &mut *(&mut raw.storage as *mut _ as *mut libc::sockaddr_in) };
+                sin.sin_family = libc::AF_INET as libc::sa_family_t;
+                sin.sin_port = addr.port().to_be();
+                sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+                raw.len = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+            }
+            SocketAddr::V6(addr) => {
+                let sin6 = unsafe { &mut *(&mut raw.storage as *mut _ as *mut libc::sockaddr_in6) };
+                sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                sin6.sin6_port = addr.port().to_be();
+                sin6.sin6_flowinfo = addr.flowinfo();
+                sin6.sin6_addr.s6_addr = addr.ip().octets();
+                sin6.sin6_scope_id = addr.scope_id();
+                raw.len = mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
+            }
+        }
+        raw
+    }
+
+    pub(super) fn unix(path: &Path) -> io::Result<Self> {
+        let mut raw = Self::empty();
+        let sun = unsafe { &mut *(&mut raw.storage as *mut _ as *mut libc::sockaddr_un) };
+        sun.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+        let bytes = path.as_os_str().as_bytes();
+        // Leave room for the terminating nul
+        if bytes.len() >= sun.sun_path.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path must be shorter than SUN_LEN",
+            ));
+        }
+        for (dst, &src) in sun.sun_path.iter_mut().zip(bytes) {
+            *dst = src as libc::c_char;
+        }
+        // `sun_path` directly follows the family
+        raw.len = (mem::size_of::<libc::sa_family_t>() + bytes.len() + 1) as libc::socklen_t;
+        Ok(raw)
+    }
+
+    pub(super) fn to_inet(self) -> io::Result<SocketAddr> {
+        match self.storage.ss_family as libc::c_int {
+            libc::AF_INET => {
+                let sin = unsafe { &*(&self.storage as *const _ as *const libc::sockaddr_in) };
+                let ip = Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes());
+                Ok(SocketAddrV4::new(ip, u16::from_be(sin.sin_port)).into())
+            }
+            libc::AF_INET6 => {
+                let sin6 = unsafe { &*(&self.storage as *const _ as *const libc::sockaddr_in6) };
+                let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+                Ok(SocketAddrV6::new(
+                    ip,
+                    u16::from_be(sin6.sin6_port),
+                    sin6.sin6_flowinfo,
+                    sin6.sin6_scope_id,
+                )
+                .into())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not an inet address",
+            )),
+        }
+    }
+
+    fn as_ptr(&self) -> *const libc::sockaddr {
+        &self.storage as *const _ as *const libc::sockaddr
+    }
+}
+
+/// Create a socket; creating one never blocks, so it skips the ring
+pub(super) fn socket(domain: libc::c_int, ty: libc::c_int) -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::socket(domain, ty | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Local or peer address of an inet socket
+pub(super) fn inet_addr(fd: RawFd, peer: bool) -> io::Result<SocketAddr> {
+    let mut addr = SockAddr::empty();
+    let storage = &mut addr.storage as *mut _ as *mut libc::sockaddr;
+    let ret = unsafe {
+        if peer {
+            libc::getpeername(fd, storage, &mut addr.len)
+        } else {
+            libc::getsockname(fd, storage, &mut addr.len)
+        }
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    addr.to_inet()
+}
+
+pub(super) struct Connect {
+    pub(super) fd: RawFd,
+    pub(super) addr: SockAddr,
+}
+
+impl PrepareOp for Connect {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_connect(sqe, self.fd, self.addr.as_ptr(), self.addr.len);
+        }
+    }
+}
+
+/// Accept one connection, recording the peer's address
+pub(super) struct Accept {
+    fd: RawFd,
+    addr: UnsafeCell<SockAddr>,
+}
+
+impl Accept {
+    pub(super) fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            addr: UnsafeCell::new(SockAddr::empty()),
+        }
+    }
+
+    pub(super) fn into_addr(self) -> SockAddr {
+        self.addr.into_inner()
+    }
+}
+
+impl PrepareOp for Accept {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        let addr = self.addr.get();
+        unsafe {
+            sys::io_uring_prep_accept(
+                sqe,
+                self.fd,
+                &mut (*addr).storage as *mut _ as *mut libc::sockaddr,
+                &mut (*addr).len,
+                libc::SOCK_CLOEXEC,
+            );
+        }
+    }
+}
+
+/// `sendmsg` of one buffer to an address
+pub(super) struct SendTo {
+    fd: RawFd,
+    buf: Vec<u8>,
+    addr: SockAddr,
+    iov: UnsafeCell<libc::iovec>,
+    msg: UnsafeCell<libc::msghdr>,
+}
+
+// SAFETY: `iov` and `msg` only point into the op itself
+unsafe impl Send for SendTo {}
+
+impl SendTo {
+    pub(super) fn new(fd: RawFd, buf: Vec<u8>, addr: SockAddr) -> Self {
+        Self {
+            fd,
+            buf,
+            addr,
+            iov: UnsafeCell::new(unsafe { mem::zeroed() }),
+            msg: UnsafeCell::new(unsafe { mem::zeroed() }),
+        }
+    }
+}
+
+impl PrepareOp for SendTo {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        // The op is boxed by now, so pointers into it stay valid
+        unsafe {
+            let iov = &mut *self.iov.get();
+            iov.iov_base = self.buf.as_ptr() as *mut _;
+            iov.iov_len = self.buf.len();
+
+            let msg = &mut *self.msg.get();
+            msg.msg_name = self.addr.as_ptr() as *mut _;
+            msg.msg_namelen = self.addr.len;
+            msg.msg_iov = iov;
+            msg.msg_iovlen = 1;
+
+            sys::io_uring_prep_sendmsg(sqe, self.fd, msg, libc::MSG_NOSIGNAL as u32);
+        }
+    }
+}
+
+/// `recvmsg` into the spare capacity of a buffer, recording the sender
+pub(super) struct RecvFrom {
+    fd: RawFd,
+    buf: Vec<u8>,
+    // Taken while `buf` is borrowed mutably; the heap block never moves
+    ptr: *mut u8,
+    // Largest datagram to receive, which `buf` may exceed in capacity
+    len: usize,
+    addr: UnsafeCell<SockAddr>,
+    iov: UnsafeCell<libc::iovec>,
+    msg: UnsafeCell<libc::msghdr>,
+}
+
+// SAFETY: `iov` and `msg` only point into the op itself
+unsafe impl Send for RecvFrom {}
+
+impl RecvFrom {
+    /// Receive a datagram of up to `len` bytes into `buf`
+    pub(super) fn new(fd: RawFd, mut buf: Vec<u8>, len: usize) -> Self {
+        buf.clear();
+        buf.reserve(len);
+        let ptr = buf.as_mut_ptr();
+        Self {
+            fd,
+            buf,
+            ptr,
+            len,
+            addr: UnsafeCell::new(SockAddr::empty()),
+            iov: UnsafeCell::new(unsafe { mem::zeroed() }),
+            msg: UnsafeCell::new(unsafe { mem::zeroed() }),
+        }
+    }
+
+    /// Take the buffer, holding the datagram, and the sender's address
+    pub(super) fn finish(self, len: usize) -> (Vec<u8>, SockAddr) {
+        let mut buf = self.buf;
+        let mut addr = self.addr.into_inner();
+        // The kernel initialized `len` bytes and updated the name length
+        unsafe { buf.set_len(len) };
+        addr.len = self.msg.into_inner().msg_namelen;
+        (buf, addr)
+    }
+}
+
+impl PrepareOp for RecvFrom {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        // The op is boxed by now, so pointers into it stay valid
+        unsafe {
+            let iov = &mut *self.iov.get();
+            iov.iov_base = self.ptr as *mut _;
+            iov.iov_len = self.len;
+
+            let addr = &mut *self.addr.get();
+            let msg = &mut *self.msg.get();
+            msg.msg_name = &mut addr.storage as *mut _ as *mut _;
+            msg.msg_namelen = addr.len;
+            msg.msg_iov = iov;
+            msg.msg_iovlen = 1;
+
+            sys::io_uring_prep_recvmsg(sqe, self.fd, msg, 0);
+        }
+    }
+}
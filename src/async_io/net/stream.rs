@@ -0,0 +1,395 @@
+//! Byte streams over connected sockets, and accepting them
+
+use super::ops::{Accept, SockAddr};
+use crate::async_io::buf::{Buf, ReadBuf, WriteBuf};
+use crate::async_io::driver::{DriverRef, OpFuture, OpStream};
+use crate::async_io::SharedHandle;
+use crate::ops::AcceptMultishot;
+use crate::Completion;
+use futures::Stream as _;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+/// Largest chunk moved by a single receive or send
+const MAX_BUF: usize = 64 * 1024;
+
+/// A connected stream socket, with independent receive and send sides
+///
+/// Both sides and every op they submit share the socket, which is closed
+/// once the last of them is gone.
+pub(super) struct Stream<H: DriverRef> {
+    pub(super) reader: Reader<H>,
+    pub(super) writer: Writer<H>,
+}
+
+impl<H: DriverRef> Stream<H> {
+    pub(super) fn new(handle: H, fd: Arc<OwnedFd>) -> Self {
+        Self {
+            reader: Reader::new(handle.clone(), fd.clone()),
+            writer: Writer::new(handle, fd),
+        }
+    }
+
+    pub(super) fn split(&mut self) -> (ReadHalf<'_, H>, WriteHalf<'_, H>) {
+        (
+            ReadHalf {
+                reader: &mut self.reader,
+            },
+            WriteHalf {
+                writer: &mut self.writer,
+            },
+        )
+    }
+
+    pub(super) fn into_split(self) -> (OwnedReadHalf<H>, OwnedWriteHalf<H>) {
+        (
+            OwnedReadHalf {
+                reader: self.reader,
+            },
+            OwnedWriteHalf {
+                writer: self.writer,
+            },
+        )
+    }
+}
+
+impl<H: DriverRef> AsRawFd for Stream<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.reader.fd.as_raw_fd()
+    }
+}
+
+/// Receive side of a stream socket
+pub(super) struct Reader<H: DriverRef> {
+    handle: H,
+    fd: Arc<OwnedFd>,
+    state: ReadState<H>,
+}
+
+enum ReadState<H: DriverRef> {
+    Idle(Buf),
+    Reading(OpFuture<H, ReadBuf>),
+}
+
+impl<H: DriverRef> Reader<H> {
+    fn new(handle: H, fd: Arc<OwnedFd>) -> Self {
+        Self {
+            handle,
+            fd,
+            state: ReadState::Idle(Buf::default()),
+        }
+    }
+
+    /// Copy received data into `dst`, receiving more if none is left over
+    pub(super) fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        dst: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match &mut self.state {
+                ReadState::Idle(buf) => {
+                    if buf.unread() > 0 || dst.is_empty() {
+                        return Poll::Ready(Ok(buf.copy_to(dst)));
+                    }
+                    let len = dst.len().min(MAX_BUF);
+                    let recv = ReadBuf::recv(self.fd.as_raw_fd(), buf.take(), len);
+                    self.state = ReadState::Reading(
+                        OpFuture::new(self.handle.clone(), recv).keep_open(self.fd.clone()),
+                    );
+                }
+                ReadState::Reading(op) => {
+                    let res = ready!(Pin::new(op).poll(cx));
+                    self.state = ReadState::Idle(Buf::default());
+
+                    let (completion, op) = res?;
+                    let (res, data) = op.finish(completion);
+                    let mut buf = Buf::new(data);
+                    res?;
+                    // Nothing at all means the peer shut down its side
+                    let n = buf.copy_to(dst);
+                    self.state = ReadState::Idle(buf);
+                    return Poll::Ready(Ok(n));
+                }
+            }
+        }
+    }
+}
+
+/// Send side of a stream socket
+///
+/// A write is copied and sent right away, and reported as done. The send
+/// is waited for by the next write, flush or shutdown, which also report
+/// its error if it failed.
+pub(super) struct Writer<H: DriverRef> {
+    handle: H,
+    fd: Arc<OwnedFd>,
+    state: WriteState<H>,
+}
+
+enum WriteState<H: DriverRef> {
+    Idle(Vec<u8>),
+    Writing(OpFuture<H, WriteBuf>),
+}
+
+impl<H: DriverRef> Writer<H> {
+    fn new(handle: H, fd: Arc<OwnedFd>) -> Self {
+        Self {
+            handle,
+            fd,
+            state: WriteState::Idle(Vec::new()),
+        }
+    }
+
+    fn op(&self, send: WriteBuf) -> OpFuture<H, WriteBuf> {
+        OpFuture::new(self.handle.clone(), send).keep_open(self.fd.clone())
+    }
+
+    /// Finish the send in flight, if any
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            let WriteState::Writing(op) = &mut self.state else {
+                return Poll::Ready(Ok(()));
+            };
+            let res = ready!(Pin::new(op).poll(cx));
+            self.state = WriteState::Idle(Vec::new());
+
+            let (completion, mut op) = res?;
+            op.advance(completion)?;
+            self.state = if op.is_done() {
+                WriteState::Idle(op.into_buf())
+            } else {
+                // Short send, carry on with the rest
+                WriteState::Writing(self.op(op))
+            };
+        }
+    }
+
+    pub(super) fn poll_write(
+        &mut self,
+        cx: &mut Context<'_>,
+        src: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_idle(cx))?;
+        if src.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let WriteState::Idle(buf) = &mut self.state else {
+            unreachable!()
+        };
+        let mut data = std::mem::take(buf);
+        data.clear();
+        let n = src.len().min(MAX_BUF);
+        data.extend_from_slice(&src[..n]);
+        let send = WriteBuf::send(self.fd.as_raw_fd(), data);
+        self.state = WriteState::Writing(self.op(send));
+
+        // Queue the SQE now; the result is picked up later
+        if let Poll::Ready(Err(e)) = self.poll_idle(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    pub(super) fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_idle(cx)
+    }
+
+    /// Flush, then shut down the sending direction
+    pub(super) fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_idle(cx))?;
+        if unsafe { libc::shutdown(self.fd.as_raw_fd(), libc::SHUT_WR) } < 0 {
+            return Poll::Ready(Err(io::Error::last_os_error()));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Borrowed receive half of a stream, from `split`
+pub struct ReadHalf<'a, H: DriverRef = SharedHandle> {
+    reader: &'a mut Reader<H>,
+}
+
+/// Borrowed send half of a stream, from `split`
+pub struct WriteHalf<'a, H: DriverRef = SharedHandle> {
+    writer: &'a mut Writer<H>,
+}
+
+/// Owned receive half of a stream, from `into_split`
+///
+/// The socket is closed once both halves are dropped.
+pub struct OwnedReadHalf<H: DriverRef = SharedHandle> {
+    reader: Reader<H>,
+}
+
+/// Owned send half of a stream, from `into_split`
+///
+/// Dropping it does not shut down the sending direction; use `shutdown`
+/// from `AsyncWriteExt` for that.
+pub struct OwnedWriteHalf<H: DriverRef = SharedHandle> {
+    writer: Writer<H>,
+}
+
+/// Implement the `AsyncRead` traits of tokio and futures-io on a type that
+/// reaches a [`Reader`] through the given fields, for any ring handle
+macro_rules! impl_async_read {
+    ($ty:ident $(<$lt:lifetime>)?, $($reader:ident).+) => {
+        #[cfg(feature = "async-tokio")]
+        impl<$($lt,)? H: $crate::async_io::driver::DriverRef> tokio::io::AsyncRead
+            for $ty<$($lt,)? H>
+        {
+            fn poll_read(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &mut tokio::io::ReadBuf<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                let n = std::task::ready!(self
+                    .get_mut()
+                    .$($reader).+
+                    .poll_read(cx, buf.initialize_unfilled()))?;
+                buf.advance(n);
+                std::task::Poll::Ready(Ok(()))
+            }
+        }
+
+        impl<$($lt,)? H: $crate::async_io::driver::DriverRef> futures::io::AsyncRead
+            for $ty<$($lt,)? H>
+        {
+            fn poll_read(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &mut [u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().$($reader).+.poll_read(cx, buf)
+            }
+        }
+    };
+}
+
+/// Implement the `AsyncWrite` traits of tokio and futures-io on a type that
+/// reaches a [`Writer`] through the given fields, for any ring handle
+macro_rules! impl_async_write {
+    ($ty:ident $(<$lt:lifetime>)?, $($writer:ident).+) => {
+        #[cfg(feature = "async-tokio")]
+        impl<$($lt,)? H: $crate::async_io::driver::DriverRef> tokio::io::AsyncWrite
+            for $ty<$($lt,)? H>
+        {
+            fn poll_write(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &[u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().$($writer).+.poll_write(cx, buf)
+            }
+
+            fn poll_flush(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                self.get_mut().$($writer).+.poll_flush(cx)
+            }
+
+            fn poll_shutdown(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                self.get_mut().$($writer).+.poll_shutdown(cx)
+            }
+        }
+
+        impl<$($lt,)? H: $crate::async_io::driver::DriverRef> futures::io::AsyncWrite
+            for $ty<$($lt,)? H>
+        {
+            fn poll_write(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+                buf: &[u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                self.get_mut().$($writer).+.poll_write(cx, buf)
+            }
+
+            fn poll_flush(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                self.get_mut().$($writer).+.poll_flush(cx)
+            }
+
+            fn poll_close(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<std::io::Result<()>> {
+                self.get_mut().$($writer).+.poll_shutdown(cx)
+            }
+        }
+    };
+}
+
+pub(super) use {impl_async_read, impl_async_write};
+
+impl_async_read!(ReadHalf<'a>, reader);
+impl_async_read!(OwnedReadHalf, reader);
+impl_async_write!(WriteHalf<'a>, writer);
+impl_async_write!(OwnedWriteHalf, writer);
+
+/// Accept a single connection
+pub(super) async fn accept<H: DriverRef>(
+    handle: &H,
+    fd: &Arc<OwnedFd>,
+) -> crate::Result<(Arc<OwnedFd>, SockAddr)> {
+    let accept = OpFuture::new(handle.clone(), Accept::new(fd.as_raw_fd()))
+        .keep_open(fd.clone())
+        .on_late_cqe(close_accepted);
+    let (completion, accept) = accept.await?;
+    let fd = completion.into_result()?;
+    Ok((
+        Arc::new(unsafe { OwnedFd::from_raw_fd(fd) }),
+        accept.into_addr(),
+    ))
+}
+
+/// Close the connection of an accept CQE that nobody takes
+fn close_accepted(completion: Completion) {
+    if let Ok(fd) = completion.into_result() {
+        drop(unsafe { OwnedFd::from_raw_fd(fd) });
+    }
+}
+
+/// Multishot accept on a listening socket, re-armed whenever it ends
+///
+/// Connections accepted but not taken by the time it is dropped, including
+/// those the kernel accepts before the cancel lands, are closed.
+pub(super) struct Acceptor<H: DriverRef> {
+    accepts: OpStream<H, AcceptMultishot>,
+}
+
+impl<H: DriverRef> Acceptor<H> {
+    pub(super) fn new(handle: H, fd: Arc<OwnedFd>) -> Self {
+        let accept = AcceptMultishot::new(fd.as_raw_fd(), libc::SOCK_CLOEXEC);
+        Self {
+            accepts: OpStream::new(handle, accept)
+                .keep_open(fd)
+                .on_late_cqe(close_accepted),
+        }
+    }
+
+    pub(super) fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Arc<OwnedFd>>> {
+        // The kernel ends a multishot accept on errors and when it runs
+        // out of resources; keep going like a loop of single accepts would
+        self.accepts.rearm();
+        let Some(res) = ready!(Pin::new(&mut self.accepts).poll_next(cx)) else {
+            // The driver failed earlier and kept the op; nothing re-arms it
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "listener's driver stopped",
+            )));
+        };
+        let fd = res?.into_result()?;
+        Poll::Ready(Ok(Arc::new(unsafe { OwnedFd::from_raw_fd(fd) })))
+    }
+}
@@ -0,0 +1,207 @@
+//! TCP listeners and streams
+
+use super::ops::{self, Connect, SockAddr};
+use super::stream::{self, Acceptor, OwnedReadHalf, OwnedWriteHalf, ReadHalf, Stream, WriteHalf};
+use crate::async_io::driver::{DriverRef, OpFuture};
+use crate::async_io::{handle, Ring, SharedHandle};
+use crate::Result;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A TCP socket listening for connections
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use futures::StreamExt;
+/// use liburing_rs::async_io::{net::TcpListener, AsyncIoUring};
+///
+/// let ring = AsyncIoUring::new(32)?;
+/// let listener = TcpListener::bind(&ring, "127.0.0.1:8080")?;
+///
+/// let mut incoming = listener.incoming();
+/// while let Some(stream) = incoming.next().await {
+///     let stream = stream?;
+///     println!("connection from {}", stream.peer_addr()?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct TcpListener<H: DriverRef = SharedHandle> {
+    handle: H,
+    // Shared with the ops on it, so it stays open until they are done
+    fd: Arc<OwnedFd>,
+}
+
+impl<H: DriverRef> TcpListener<H> {
+    /// Bind to the first of `addr` that works and start listening
+    pub fn bind(ring: &impl Ring<Handle = H>, addr: impl ToSocketAddrs) -> Result<TcpListener<H>> {
+        Ok(Self::from_std(ring, std::net::TcpListener::bind(addr)?))
+    }
+
+    /// Wrap a listener created with the standard library
+    pub fn from_std(
+        ring: &impl Ring<Handle = H>,
+        listener: std::net::TcpListener,
+    ) -> TcpListener<H> {
+        TcpListener {
+            handle: handle(ring),
+            fd: Arc::new(listener.into()),
+        }
+    }
+
+    /// Accept one connection
+    pub async fn accept(&self) -> Result<(TcpStream<H>, SocketAddr)> {
+        let (fd, addr) = stream::accept(&self.handle, &self.fd).await?;
+        Ok((
+            TcpStream::from_parts(self.handle.clone(), fd),
+            addr.to_inet()?,
+        ))
+    }
+
+    /// Stream of incoming connections
+    ///
+    /// Backed by a single multishot accept, so a stream of connections
+    /// costs one submission rather than one per connection. Connections
+    /// accepted after the last one taken are closed when it is dropped.
+    pub fn incoming(&self) -> Incoming<'_, H> {
+        Incoming {
+            listener: self,
+            acceptor: Acceptor::new(self.handle.clone(), self.fd.clone()),
+        }
+    }
+
+    /// Address the socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(ops::inet_addr(self.fd.as_raw_fd(), false)?)
+    }
+}
+
+impl<H: DriverRef> AsRawFd for TcpListener<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl<H: DriverRef> AsFd for TcpListener<H> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// Connections accepted by a [`TcpListener`], from
+/// [`incoming`](TcpListener::incoming)
+pub struct Incoming<'a, H: DriverRef = SharedHandle> {
+    listener: &'a TcpListener<H>,
+    acceptor: Acceptor<H>,
+}
+
+impl<H: DriverRef> futures::Stream for Incoming<'_, H> {
+    type Item = Result<TcpStream<H>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.acceptor.poll_accept(cx).map(|res| {
+            let handle = this.listener.handle.clone();
+            Some(
+                res.map(|fd| TcpStream::from_parts(handle, fd))
+                    .map_err(Into::into),
+            )
+        })
+    }
+}
+
+/// A connected TCP socket
+///
+/// Implements the `AsyncRead` and `AsyncWrite` traits of tokio and of
+/// `futures-io`. A write is copied and sent right away; `flush` waits for
+/// the send and reports its result. Dropping the stream cancels a send
+/// that is still in flight.
+pub struct TcpStream<H: DriverRef = SharedHandle> {
+    inner: Stream<H>,
+}
+
+impl<H: DriverRef> TcpStream<H> {
+    /// Connect to `addr`
+    pub async fn connect(ring: &impl Ring<Handle = H>, addr: SocketAddr) -> Result<TcpStream<H>> {
+        let domain = match addr {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+        let fd = Arc::new(ops::socket(domain, libc::SOCK_STREAM)?);
+
+        let handle = handle(ring);
+        let connect = Connect {
+            fd: fd.as_raw_fd(),
+            addr: SockAddr::inet(&addr),
+        };
+        let connect = OpFuture::new(handle.clone(), connect).keep_open(fd.clone());
+        let (completion, _) = connect.await?;
+        completion.into_result()?;
+        Ok(Self::from_parts(handle, fd))
+    }
+
+    /// Wrap a stream created with the standard library
+    pub fn from_std(ring: &impl Ring<Handle = H>, stream: std::net::TcpStream) -> TcpStream<H> {
+        Self::from_parts(handle(ring), Arc::new(stream.into()))
+    }
+
+    fn from_parts(handle: H, fd: Arc<OwnedFd>) -> TcpStream<H> {
+        TcpStream {
+            inner: Stream::new(handle, fd),
+        }
+    }
+
+    /// Address the socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(ops::inet_addr(self.as_raw_fd(), false)?)
+    }
+
+    /// Address of the connected peer
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        Ok(ops::inet_addr(self.as_raw_fd(), true)?)
+    }
+
+    /// Set `TCP_NODELAY`, disabling Nagle's algorithm when `nodelay` is true
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<()> {
+        let value = nodelay as libc::c_int;
+        let ret = unsafe {
+            libc::setsockopt(
+                self.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_NODELAY,
+                &value as *const _ as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Borrow the receive and send sides separately
+    pub fn split(&mut self) -> (ReadHalf<'_, H>, WriteHalf<'_, H>) {
+        self.inner.split()
+    }
+
+    /// Split into halves that can be moved to different tasks
+    pub fn into_split(self) -> (OwnedReadHalf<H>, OwnedWriteHalf<H>) {
+        self.inner.into_split()
+    }
+}
+
+impl<H: DriverRef> AsRawFd for TcpStream<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+stream::impl_async_read!(TcpStream, inner.reader);
+stream::impl_async_write!(TcpStream, inner.writer);
@@ -0,0 +1,92 @@
+//! UDP sockets
+
+use super::ops::{self, RecvFrom, SendTo, SockAddr};
+use crate::async_io::driver::{DriverRef, OpFuture};
+use crate::async_io::{handle, Ring, SharedHandle};
+use crate::Result;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::sync::Arc;
+
+/// A UDP socket
+///
+/// Datagrams go through `sendmsg` and `recvmsg` on the ring. Both copy
+/// through a buffer owned by the op, so the caller's slice is never left
+/// with the kernel.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use liburing_rs::async_io::{net::UdpSocket, AsyncIoUring};
+///
+/// let ring = AsyncIoUring::new(32)?;
+/// let socket = UdpSocket::bind(&ring, "127.0.0.1:0")?;
+///
+/// let mut buf = [0u8; 1500];
+/// let (n, peer) = socket.recv_from(&mut buf).await?;
+/// socket.send_to(&buf[..n], peer).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct UdpSocket<H: DriverRef = SharedHandle> {
+    handle: H,
+    // Shared with the ops on it, so it stays open until they are done
+    fd: Arc<OwnedFd>,
+}
+
+impl<H: DriverRef> UdpSocket<H> {
+    /// Bind to the first of `addr` that works
+    pub fn bind(ring: &impl Ring<Handle = H>, addr: impl ToSocketAddrs) -> Result<UdpSocket<H>> {
+        Ok(Self::from_std(ring, std::net::UdpSocket::bind(addr)?))
+    }
+
+    /// Wrap a socket created with the standard library
+    pub fn from_std(ring: &impl Ring<Handle = H>, socket: std::net::UdpSocket) -> UdpSocket<H> {
+        UdpSocket {
+            handle: handle(ring),
+            fd: Arc::new(socket.into()),
+        }
+    }
+
+    /// Send `buf` as one datagram to `addr`, returning the bytes sent
+    pub async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        let send = SendTo::new(self.fd.as_raw_fd(), buf.to_vec(), SockAddr::inet(&addr));
+        let (completion, _) = OpFuture::new(self.handle.clone(), send)
+            .keep_open(self.fd.clone())
+            .await?;
+        Ok(completion.into_result()? as usize)
+    }
+
+    /// Receive one datagram into `buf`, returning its length and sender
+    ///
+    /// The rest of a datagram that doesn't fit in `buf` is discarded.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        let recv = RecvFrom::new(self.fd.as_raw_fd(), Vec::new(), buf.len());
+        let (completion, recv) = OpFuture::new(self.handle.clone(), recv)
+            .keep_open(self.fd.clone())
+            .await?;
+        let n = completion.into_result()? as usize;
+        let (data, addr) = recv.finish(n);
+        buf[..n].copy_from_slice(&data);
+        Ok((n, addr.to_inet()?))
+    }
+
+    /// Address the socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(ops::inet_addr(self.fd.as_raw_fd(), false)?)
+    }
+}
+
+impl<H: DriverRef> AsRawFd for UdpSocket<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl<H: DriverRef> AsFd for UdpSocket<H> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
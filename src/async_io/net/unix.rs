@@ -0,0 +1,154 @@
+//! Unix domain stream sockets
+
+use super::ops::{self, Connect, SockAddr};
+use super::stream::{self, Acceptor, OwnedReadHalf, OwnedWriteHalf, ReadHalf, Stream, WriteHalf};
+use crate::async_io::driver::{DriverRef, OpFuture};
+use crate::async_io::{handle, Ring, SharedHandle};
+use crate::Result;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A Unix domain socket listening for connections
+pub struct UnixListener<H: DriverRef = SharedHandle> {
+    handle: H,
+    // Shared with the ops on it, so it stays open until they are done
+    fd: Arc<OwnedFd>,
+}
+
+impl<H: DriverRef> UnixListener<H> {
+    /// Bind to `path` and start listening
+    pub fn bind(ring: &impl Ring<Handle = H>, path: impl AsRef<Path>) -> Result<UnixListener<H>> {
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        Ok(Self::from_std(ring, listener))
+    }
+
+    /// Wrap a listener created with the standard library
+    pub fn from_std(
+        ring: &impl Ring<Handle = H>,
+        listener: std::os::unix::net::UnixListener,
+    ) -> UnixListener<H> {
+        UnixListener {
+            handle: handle(ring),
+            fd: Arc::new(listener.into()),
+        }
+    }
+
+    /// Accept one connection
+    pub async fn accept(&self) -> Result<UnixStream<H>> {
+        let (fd, _) = stream::accept(&self.handle, &self.fd).await?;
+        Ok(UnixStream::from_parts(self.handle.clone(), fd))
+    }
+
+    /// Stream of incoming connections, backed by a multishot accept
+    pub fn incoming(&self) -> UnixIncoming<'_, H> {
+        UnixIncoming {
+            listener: self,
+            acceptor: Acceptor::new(self.handle.clone(), self.fd.clone()),
+        }
+    }
+}
+
+impl<H: DriverRef> AsRawFd for UnixListener<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl<H: DriverRef> AsFd for UnixListener<H> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// Connections accepted by a [`UnixListener`], from
+/// [`incoming`](UnixListener::incoming)
+pub struct UnixIncoming<'a, H: DriverRef = SharedHandle> {
+    listener: &'a UnixListener<H>,
+    acceptor: Acceptor<H>,
+}
+
+impl<H: DriverRef> futures::Stream for UnixIncoming<'_, H> {
+    type Item = Result<UnixStream<H>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.acceptor.poll_accept(cx).map(|res| {
+            let handle = this.listener.handle.clone();
+            Some(
+                res.map(|fd| UnixStream::from_parts(handle, fd))
+                    .map_err(Into::into),
+            )
+        })
+    }
+}
+
+/// A connected Unix domain stream socket
+///
+/// Implements the `AsyncRead` and `AsyncWrite` traits of tokio and of
+/// `futures-io`, like [`TcpStream`](super::TcpStream).
+pub struct UnixStream<H: DriverRef = SharedHandle> {
+    inner: Stream<H>,
+}
+
+impl<H: DriverRef> UnixStream<H> {
+    /// Connect to the socket bound to `path`
+    pub async fn connect(
+        ring: &impl Ring<Handle = H>,
+        path: impl AsRef<Path>,
+    ) -> Result<UnixStream<H>> {
+        let addr = SockAddr::unix(path.as_ref())?;
+        let fd = Arc::new(ops::socket(libc::AF_UNIX, libc::SOCK_STREAM)?);
+
+        let handle = handle(ring);
+        let connect = Connect {
+            fd: fd.as_raw_fd(),
+            addr,
+        };
+        let connect = OpFuture::new(handle.clone(), connect).keep_open(fd.clone());
+        let (completion, _) = connect.await?;
+        completion.into_result()?;
+        Ok(Self::from_parts(handle, fd))
+    }
+
+    /// Create a pair of connected sockets
+    pub fn pair(ring: &impl Ring<Handle = H>) -> Result<(UnixStream<H>, UnixStream<H>)> {
+        let (a, b) = std::os::unix::net::UnixStream::pair()?;
+        Ok((Self::from_std(ring, a), Self::from_std(ring, b)))
+    }
+
+    /// Wrap a stream created with the standard library
+    pub fn from_std(
+        ring: &impl Ring<Handle = H>,
+        stream: std::os::unix::net::UnixStream,
+    ) -> UnixStream<H> {
+        Self::from_parts(handle(ring), Arc::new(stream.into()))
+    }
+
+    fn from_parts(handle: H, fd: Arc<OwnedFd>) -> UnixStream<H> {
+        UnixStream {
+            inner: Stream::new(handle, fd),
+        }
+    }
+
+    /// Borrow the receive and send sides separately
+    pub fn split(&mut self) -> (ReadHalf<'_, H>, WriteHalf<'_, H>) {
+        self.inner.split()
+    }
+
+    /// Split into halves that can be moved to different tasks
+    pub fn into_split(self) -> (OwnedReadHalf<H>, OwnedWriteHalf<H>) {
+        self.inner.into_split()
+    }
+}
+
+impl<H: DriverRef> AsRawFd for UnixStream<H> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+stream::impl_async_read!(UnixStream, inner.reader);
+stream::impl_async_write!(UnixStream, inner.writer);
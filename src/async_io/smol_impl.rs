@@ -0,0 +1,127 @@
+//! smol and async-io integration for io_uring
+
+use super::driver::{self, AsyncIoReadiness, Driver, OpFuture, Reactor};
+use super::multishot::Multishot;
+use crate::{ops::PrepareOp, Result};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Async io_uring instance waiting on the ring through `async-io`
+///
+/// `async-io` has no executor of its own, so the reactor that submits SQEs
+/// and dispatches CQEs is handed out by [`reactor`](Self::reactor) for the
+/// caller to spawn, on smol or on any other executor. The reactor only
+/// needs to be polled; `async-io` wakes it when the ring becomes readable.
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::async_io::smol_impl::AsyncIoUring;
+/// use liburing_rs::ops::Nop;
+///
+/// # fn main() -> liburing_rs::Result<()> {
+/// let ring = AsyncIoUring::new(32)?;
+/// // On smol: `smol::spawn(ring.reactor()).detach()`
+/// let reactor = ring.reactor();
+/// std::thread::spawn(move || async_io::block_on(reactor));
+///
+/// let result = async_io::block_on(ring.submit_op(Nop))?;
+/// println!("NOP completed with result: {}", result);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncIoUring {
+    reactor: Arc<Reactor<AsyncIoReadiness>>,
+}
+
+impl AsyncIoUring {
+    /// Create a new async io_uring instance with the specified number of entries
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Number of submission queue entries (will be rounded up to power of 2)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kernel doesn't support io_uring or if setup fails.
+    pub fn new(entries: u32) -> Result<Self> {
+        Self::setup(entries, false)
+    }
+
+    /// Create a new async io_uring instance that is woken through an eventfd
+    ///
+    /// The eventfd is registered with the ring and polled in place of the
+    /// ring fd.
+    pub fn with_eventfd(entries: u32) -> Result<Self> {
+        Self::setup(entries, true)
+    }
+
+    fn setup(entries: u32, use_eventfd: bool) -> Result<Self> {
+        let (ring, eventfd) = driver::setup_ring(entries, use_eventfd)?;
+        let driver = Driver::new(ring, eventfd);
+        let readiness = AsyncIoReadiness::new(&driver)?;
+
+        Ok(Self {
+            reactor: Arc::new(Reactor::new(readiness, driver)),
+        })
+    }
+
+    /// The reactor task of this ring, to be spawned on an executor
+    ///
+    /// Ops make no progress until it runs. It completes once every handle
+    /// to the ring and every op is gone, or when the ring fails. Spawning
+    /// more than one reactor for a ring is harmless but pointless.
+    pub fn reactor(&self) -> impl Future<Output = ()> + Send + 'static {
+        driver::run_reactor(Arc::downgrade(&self.reactor))
+    }
+
+    /// Submit an operation and wait for its completion asynchronously
+    ///
+    /// The SQE is queued on the first poll and submitted together with the
+    /// other SQEs queued before the reactor next runs.
+    ///
+    /// The op is owned by the future until its CQE arrives, so any buffer
+    /// it points into stays valid. If the future is dropped while the op is
+    /// in flight, the op is cancelled with `IORING_OP_ASYNC_CANCEL` and kept
+    /// alive by the driver until the kernel posts its final completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The operation to submit (implements `PrepareOp`)
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to the result code of the operation
+    pub fn submit_op<Op: PrepareOp + Send + 'static>(
+        &self,
+        op: Op,
+    ) -> impl Future<Output = Result<i32>> {
+        let op = OpFuture::new(self.reactor.clone(), op);
+        async move { op.await.map(|(completion, _)| completion.result()) }
+    }
+
+    /// Submit a multishot operation and stream its completions
+    ///
+    /// Every CQE the op posts is yielded in order; the stream ends with the
+    /// one without `IORING_CQE_F_MORE`. Call [`Multishot::rearm`] on the
+    /// result to keep the op armed instead. Dropping the stream cancels the
+    /// op.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The multishot operation to submit (implements `PrepareOp`)
+    pub fn submit_multishot<Op: PrepareOp + Send + 'static>(&self, op: Op) -> Multishot<Op> {
+        Multishot::new(self.reactor.clone(), op)
+    }
+}
+
+impl super::sealed::Sealed for AsyncIoUring {
+    type Handle = super::SharedHandle;
+
+    fn ring_handle(&self) -> super::SharedHandle {
+        super::SharedHandle(self.reactor.clone())
+    }
+}
+
+impl super::Ring for AsyncIoUring {}
@@ -0,0 +1,249 @@
+//! Timers on top of an async ring
+//!
+//! Every timer is an `IORING_OP_TIMEOUT` on the [`Ring`] it was created
+//! with, so a thread that drives a ring needs no timer wheel of its own and
+//! deadlines are kept by the kernel. [`Interval`] uses a multishot timeout,
+//! and [`timeout_op`] bounds a single op with `IORING_OP_LINK_TIMEOUT`, as
+//! [`timeout`] does with each op its future submits.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use liburing_rs::async_io::{time, AsyncIoUring};
+//! use liburing_rs::ops::Accept;
+//! use std::os::unix::io::RawFd;
+//! use std::ptr;
+//! use std::time::Duration;
+//!
+//! # async fn example(ring: &AsyncIoUring, listener: RawFd) -> liburing_rs::Result<()> {
+//! time::sleep(ring, Duration::from_millis(10)).await?;
+//!
+//! // The kernel cancels the accept if no client shows up within a second
+//! let accept = unsafe { Accept::new(listener, ptr::null_mut(), ptr::null_mut(), 0) };
+//! match time::timeout_op(ring, Duration::from_secs(1), accept).await {
+//!     Ok(fd) => println!("accepted fd {}", fd),
+//!     Err(e) => println!("gave up: {}", e),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::driver::{Deadline, DriverRef, OpFuture, OpStream};
+use super::multishot::poll_rearming;
+use super::{handle, Ring, SharedHandle};
+use crate::{ops, ops::PrepareOp, Completion, Error, Result};
+use futures::Stream;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Wait until `duration` has passed
+///
+/// The duration counts from this call, however late the returned future is
+/// first polled. The timeout is submitted on that first poll, and cancelled
+/// if the future is dropped before it fires.
+pub fn sleep<R: Ring>(ring: &R, duration: Duration) -> Sleep<R::Handle> {
+    sleep_until(ring, Instant::now() + duration)
+}
+
+/// Wait until `deadline`
+///
+/// The timeout is an absolute one on `CLOCK_MONOTONIC`, the clock of
+/// `Instant`, so it fires at `deadline` whenever it is submitted.
+pub fn sleep_until<R: Ring>(ring: &R, deadline: Instant) -> Sleep<R::Handle> {
+    Sleep {
+        op: OpFuture::new(handle(ring), ops::Timeout::at(deadline)),
+        deadline,
+    }
+}
+
+/// Tick every `period`, starting one period from now
+///
+/// Ticks come from a single multishot timeout, which needs Linux 6.4.
+pub fn interval<R: Ring>(ring: &R, period: Duration) -> Interval<R::Handle> {
+    Interval {
+        ticks: OpStream::new(handle(ring), ops::Timeout::multishot(period, 0)),
+        period,
+    }
+}
+
+/// Run `future`, giving up once `duration` has passed
+///
+/// Every op `future` submits while it runs, such as the one of
+/// `submit_op`, is linked to an `IORING_OP_LINK_TIMEOUT` at the deadline,
+/// so the kernel cancels it by itself, like [`timeout_op`] does. Anything
+/// else `future` waits for is dropped on expiry, which cancels ops it
+/// still has in flight with `IORING_OP_ASYNC_CANCEL`.
+///
+/// # Errors
+///
+/// Resolves with an error of kind [`TimedOut`](io::ErrorKind::TimedOut)
+/// if the duration passed first.
+pub fn timeout<R: Ring, F: Future>(
+    ring: &R,
+    duration: Duration,
+    future: F,
+) -> Timeout<F, R::Handle> {
+    let deadline = Instant::now() + duration;
+    Timeout {
+        future,
+        sleep: sleep_until(ring, deadline),
+        deadline: Deadline::new(deadline),
+    }
+}
+
+/// Submit `op` linked to an `IORING_OP_LINK_TIMEOUT` of `duration`
+///
+/// The kernel cancels the op if it hasn't completed in time, without a
+/// round-trip through the ring's owner. Resolves with the op's result code
+/// like `submit_op`.
+///
+/// # Errors
+///
+/// Resolves with an error of kind [`TimedOut`](io::ErrorKind::TimedOut)
+/// if the timeout cancelled the op.
+pub fn timeout_op<Op: PrepareOp + Send + 'static>(
+    ring: &impl Ring,
+    duration: Duration,
+    op: Op,
+) -> impl Future<Output = Result<i32>> {
+    let op = OpFuture::with_timeout(handle(ring), op, duration);
+    async move {
+        let (completion, _) = op.await?;
+        match completion.result() {
+            res if res == -libc::ECANCELED => Err(timed_out()),
+            res => Ok(res),
+        }
+    }
+}
+
+/// Future returned by [`sleep`] and [`sleep_until`]
+pub struct Sleep<H: DriverRef = SharedHandle> {
+    op: OpFuture<H, ops::Timeout>,
+    deadline: Instant,
+}
+
+impl<H: DriverRef> Sleep<H> {
+    /// When the sleep ends
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Whether the sleep has ended
+    pub fn is_elapsed(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+}
+
+impl<H: DriverRef> Future for Sleep<H> {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let (completion, _) = ready!(Pin::new(&mut self.op).poll(cx))?;
+        Poll::Ready(expired(&completion))
+    }
+}
+
+/// Stream of ticks returned by [`interval`]
+///
+/// Ticks are counted by the kernel; a tick is not delayed by a late
+/// consumer, but ticks that arrive while nobody polls are all yielded. As a
+/// stream it ends after yielding an error the timeout would hit again.
+pub struct Interval<H: DriverRef = SharedHandle> {
+    // Re-armed whenever the kernel ends it, unless it failed
+    ticks: OpStream<H, ops::Timeout>,
+    period: Duration,
+}
+
+impl<H: DriverRef> Interval<H> {
+    /// Wait for the next tick
+    pub async fn tick(&mut self) -> Result<()> {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Poll for the next tick
+    ///
+    /// Once the timeout failed for good, every poll returns an error.
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        match ready!(self.poll_next_tick(cx)) {
+            Some(tick) => Poll::Ready(tick),
+            None => Poll::Ready(Err(Error::InvalidOperation(
+                "interval timeout ended".to_string(),
+            ))),
+        }
+    }
+
+    fn poll_next_tick(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<()>>> {
+        let completion = ready!(poll_rearming(&mut self.ticks, true, cx));
+        Poll::Ready(completion.map(|c| c.and_then(|c| expired(&c))))
+    }
+
+    /// Time between ticks
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+impl<H: DriverRef> Stream for Interval<H> {
+    type Item = Result<()>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<()>>> {
+        self.get_mut().poll_next_tick(cx)
+    }
+}
+
+/// Future returned by [`timeout`]
+pub struct Timeout<F, H: DriverRef = SharedHandle> {
+    future: F,
+    sleep: Sleep<H>,
+    // What the ops `future` submits are linked to
+    deadline: Deadline,
+}
+
+impl<F, H: DriverRef> Timeout<F, H> {
+    /// The future being run
+    pub fn get_ref(&self) -> &F {
+        &self.future
+    }
+
+    /// Stop the timer, handing back the future
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future, H: DriverRef> Future for Timeout<F, H> {
+    type Output = Result<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved out of the pinned struct, and
+        // `sleep` is `Unpin`
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+        if let Poll::Ready(output) = this.deadline.enter(|| future.poll(cx)) {
+            // An op the kernel cancelled at the deadline ends the future too
+            if this.deadline.expired() {
+                return Poll::Ready(Err(timed_out()));
+            }
+            return Poll::Ready(Ok(output));
+        }
+        ready!(Pin::new(&mut this.sleep).poll(cx))?;
+        Poll::Ready(Err(timed_out()))
+    }
+}
+
+/// The result of a timeout CQE: `-ETIME` when it fired
+fn expired(completion: &Completion) -> Result<()> {
+    match completion.result() {
+        res if res == -libc::ETIME => Ok(()),
+        res if res < 0 => Err(Error::Io(io::Error::from_raw_os_error(-res))),
+        _ => Ok(()),
+    }
+}
+
+fn timed_out() -> Error {
+    Error::Io(io::ErrorKind::TimedOut.into())
+}
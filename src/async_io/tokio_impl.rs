@@ -1,14 +1,12 @@
 //! Tokio async runtime integration for io_uring
 
-use crate::{
-    ops::{PrepareOp, SqeExt},
-    Error, IoUring, Result,
-};
-use std::collections::HashMap;
+use super::driver::{self, Driver, OpFuture, Reactor, Readiness, RingFd};
+use super::multishot::Multishot;
+use crate::{ops::PrepareOp, Error, Result};
 use std::future::Future;
-use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-use std::task::{Context, Poll, Waker};
+use std::io;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 use tokio::io::unix::AsyncFd;
 use tokio::io::Interest;
 
@@ -17,6 +15,11 @@ use tokio::io::Interest;
 /// This wraps an `IoUring` instance and integrates it with tokio's async runtime,
 /// allowing you to use async/await with io_uring operations.
 ///
+/// A reactor task spawned on the current runtime submits queued SQEs in
+/// batches and dispatches every CQE to the operation it belongs to, so any
+/// number of operations can be in flight at once. Handles are cheap to
+/// clone, and the reactor exits once the last handle and operation are gone.
+///
 /// # Example
 ///
 /// ```no_run
@@ -25,7 +28,7 @@ use tokio::io::Interest;
 /// use liburing_rs::async_io::AsyncIoUring;
 /// use liburing_rs::ops::Nop;
 ///
-/// let mut ring = AsyncIoUring::new(32)?;
+/// let ring = AsyncIoUring::new(32)?;
 ///
 /// // Submit a NOP operation and await its completion
 /// let result = ring.submit_op(Nop).await?;
@@ -33,23 +36,18 @@ use tokio::io::Interest;
 /// # Ok(())
 /// # }
 /// ```
+#[derive(Clone)]
 pub struct AsyncIoUring {
-    inner: Arc<Mutex<AsyncIoUringInner>>,
-}
-
-struct AsyncIoUringInner {
-    ring: IoUring,
-    async_fd: AsyncFd<RawFdWrapper>,
-    wakers: HashMap<u64, Waker>,
-    next_user_data: u64,
+    reactor: Arc<Reactor<TokioReadiness>>,
 }
 
-/// Wrapper to make RawFd work with AsyncFd
-struct RawFdWrapper(std::os::unix::io::RawFd);
+struct TokioReadiness(AsyncFd<RingFd>);
 
-impl std::os::unix::io::AsRawFd for RawFdWrapper {
-    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+impl Readiness for TokioReadiness {
+    fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         self.0
+            .poll_read_ready(cx)
+            .map_ok(|mut guard| guard.clear_ready())
     }
 }
 
@@ -63,28 +61,46 @@ impl AsyncIoUring {
     /// # Errors
     ///
     /// Returns an error if the kernel doesn't support io_uring or if setup fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
     pub fn new(entries: u32) -> Result<Self> {
-        let ring = IoUring::new(entries)?;
-        let fd = ring.as_raw_fd();
+        Self::setup(entries, false)
+    }
+
+    /// Create a new async io_uring instance that is woken through an eventfd
+    ///
+    /// The eventfd is registered with the ring and polled by tokio in place
+    /// of the ring fd, which suits rings whose fd is closed or otherwise
+    /// unsuitable for readiness polling.
+    pub fn with_eventfd(entries: u32) -> Result<Self> {
+        Self::setup(entries, true)
+    }
 
-        // Wrap the fd in our wrapper type
-        let fd_wrapper = RawFdWrapper(fd);
+    fn setup(entries: u32, use_eventfd: bool) -> Result<Self> {
+        let (ring, eventfd) = driver::setup_ring(entries, use_eventfd)?;
+        let driver = Driver::new(ring, eventfd);
+        // The fd becomes readable when completions arrive
+        let async_fd =
+            AsyncFd::with_interest(driver.readiness_fd(), Interest::READABLE).map_err(Error::Io)?;
 
-        // Create AsyncFd with READABLE interest (io_uring fd becomes readable when completions arrive)
-        let async_fd = AsyncFd::with_interest(fd_wrapper, Interest::READABLE).map_err(Error::Io)?;
+        let reactor = Arc::new(Reactor::new(TokioReadiness(async_fd), driver));
+        tokio::spawn(driver::run_reactor(Arc::downgrade(&reactor)));
 
-        Ok(Self {
-            inner: Arc::new(Mutex::new(AsyncIoUringInner {
-                ring,
-                async_fd,
-                wakers: HashMap::new(),
-                next_user_data: 1,
-            })),
-        })
+        Ok(Self { reactor })
     }
 
     /// Submit an operation and wait for its completion asynchronously
     ///
+    /// The SQE is queued on the first poll and submitted together with the
+    /// other SQEs queued before the reactor next runs.
+    ///
+    /// The op is owned by the future until its CQE arrives, so any buffer
+    /// it points into stays valid. If the future is dropped while the op is
+    /// in flight, the op is cancelled with `IORING_OP_ASYNC_CANCEL` and kept
+    /// alive by the driver until the kernel posts its final completion.
+    ///
     /// # Arguments
     ///
     /// * `op` - The operation to submit (implements `PrepareOp`)
@@ -92,111 +108,39 @@ impl AsyncIoUring {
     /// # Returns
     ///
     /// A future that resolves to the result code of the operation
-    pub fn submit_op<Op: PrepareOp + 'static>(
-        &mut self,
+    pub fn submit_op<Op: PrepareOp + Send + 'static>(
+        &self,
         op: Op,
     ) -> impl Future<Output = Result<i32>> {
-        SubmitFuture {
-            ring: self.inner.clone(),
-            op: Some(op),
-            user_data: None,
-        }
+        let op = OpFuture::new(self.reactor.clone(), op);
+        async move { op.await.map(|(completion, _)| completion.result()) }
     }
-}
-
-struct SubmitFuture<Op> {
-    ring: Arc<Mutex<AsyncIoUringInner>>,
-    op: Option<Op>,
-    user_data: Option<u64>,
-}
 
-// SubmitFuture doesn't need to be pinned
-impl<Op> Unpin for SubmitFuture<Op> {}
-
-impl<Op: PrepareOp> Future for SubmitFuture<Op> {
-    type Output = Result<i32>;
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let future = self.get_mut();
-        let mut inner = future.ring.lock().unwrap();
-
-        // If we haven't submitted yet, do so now
-        if let Some(op) = future.op.take() {
-            // Get the next user_data value
-            let user_data = inner.next_user_data;
-            inner.next_user_data = inner.next_user_data.wrapping_add(1);
-            future.user_data = Some(user_data);
-
-            // Submit the operation
-            {
-                let mut sq = inner.ring.submission();
-                let sqe = match sq.get_sqe_or_err() {
-                    Ok(sqe) => sqe,
-                    Err(e) => return Poll::Ready(Err(e)),
-                };
-                op.prepare(sqe);
-                sqe.set_user_data(user_data);
-            }
-
-            // Submit to kernel
-            if let Err(e) = inner.ring.submit() {
-                return Poll::Ready(Err(e));
-            }
-
-            // Register our waker
-            inner.wakers.insert(user_data, cx.waker().clone());
-        }
-
-        // Try to get completion
-        let user_data = future.user_data.unwrap();
+    /// Submit a multishot operation and stream its completions
+    ///
+    /// Every CQE the op posts is yielded in order; the stream ends with the
+    /// one without `IORING_CQE_F_MORE`. Call [`Multishot::rearm`] on the
+    /// result to keep the op armed instead. Dropping the stream cancels the
+    /// op.
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - The multishot operation to submit (implements `PrepareOp`)
+    pub fn submit_multishot<Op: PrepareOp + Send + 'static>(&self, op: Op) -> Multishot<Op> {
+        Multishot::new(self.handle(), op)
+    }
 
-        // Check for completions
-        loop {
-            let cqe_data: Option<(u64, i32)> = {
-                let mut cq = inner.ring.completion();
-                cq.peek_cqe().map(|cqe| (cqe.user_data(), cqe.result()))
-            };
+    pub(crate) fn handle(&self) -> driver::Handle {
+        self.reactor.clone()
+    }
+}
 
-            match cqe_data {
-                Some((cqe_user_data, result)) => {
-                    if cqe_user_data == user_data {
-                        // This is our completion
-                        inner.wakers.remove(&user_data);
-                        return Poll::Ready(Ok(result));
-                    } else {
-                        // Wake up the other task
-                        if let Some(waker) = inner.wakers.remove(&cqe_user_data) {
-                            waker.wake();
-                        }
-                    }
-                }
-                None => {
-                    // No more completions available, wait for fd to become readable
-                    break;
-                }
-            }
-        }
+impl super::sealed::Sealed for AsyncIoUring {
+    type Handle = super::SharedHandle;
 
-        // Wait for the fd to become readable (more completions available)
-        match inner.async_fd.poll_read_ready(cx) {
-            Poll::Ready(Ok(mut guard)) => {
-                // Clear the ready state
-                guard.clear_ready();
-                // Re-register our waker and return pending
-                // The next poll will check for completions again
-                inner.wakers.insert(user_data, cx.waker().clone());
-                Poll::Pending
-            }
-            Poll::Ready(Err(e)) => Poll::Ready(Err(Error::Io(e))),
-            Poll::Pending => {
-                // Make sure our waker is registered
-                inner.wakers.insert(user_data, cx.waker().clone());
-                Poll::Pending
-            }
-        }
+    fn ring_handle(&self) -> super::SharedHandle {
+        super::SharedHandle(self.handle())
     }
 }
 
-// AsyncIoUring can be sent between threads
-unsafe impl Send for AsyncIoUring {}
-unsafe impl Sync for AsyncIoUring {}
+impl super::Ring for AsyncIoUring {}
@@ -0,0 +1,469 @@
+//! Builder for io_uring setup parameters
+
+use crate::error::{Error, Result};
+use crate::flags::SetupFlags;
+use crate::napi::NapiConfig;
+use crate::queue::BackpressurePolicy;
+use crate::sys;
+use crate::uring::IoUring;
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+/// Builder for an [`IoUring`] instance
+///
+/// Collects the fields of `io_uring_params` through typed setters and checks
+/// incompatible combinations before `io_uring_setup` is called, so a bad
+/// configuration is reported as [`Error::InvalidParameter`] naming the field
+/// at fault instead of a bare `EINVAL` from the kernel.
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::IoUring;
+///
+/// let ring = IoUring::builder()
+///     .cq_entries(256)
+///     .single_issuer()
+///     .defer_taskrun()
+///     .build(64)?;
+/// # Ok::<(), liburing_rs::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct IoUringBuilder {
+    flags: SetupFlags,
+    cq_entries: Option<u32>,
+    sq_thread_idle: Option<Duration>,
+    sq_thread_cpu: Option<u32>,
+    wq_fd: Option<RawFd>,
+    memory: Option<RingMemory>,
+    backpressure: BackpressurePolicy,
+    napi: Option<NapiConfig>,
+}
+
+impl IoUringBuilder {
+    /// Create a builder with no flags set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Size the completion queue explicitly (`IORING_SETUP_CQSIZE`)
+    ///
+    /// By default the kernel allocates twice as many CQ entries as SQ entries.
+    pub fn cq_entries(mut self, entries: u32) -> Self {
+        self.flags |= SetupFlags::CQSIZE;
+        self.cq_entries = Some(entries);
+        self
+    }
+
+    /// Clamp the SQ and CQ sizes to the kernel maximum instead of failing
+    pub fn clamp(mut self) -> Self {
+        self.flags |= SetupFlags::CLAMP;
+        self
+    }
+
+    /// Use busy-polling for I/O completions (`IORING_SETUP_IOPOLL`)
+    pub fn iopoll(mut self) -> Self {
+        self.flags |= SetupFlags::IOPOLL;
+        self
+    }
+
+    /// Let a kernel thread poll the submission queue (`IORING_SETUP_SQPOLL`)
+    ///
+    /// On kernels without `IORING_FEAT_SQPOLL_NONFIXED` (before 5.11) only
+    /// registered files may be used with an SQPOLL ring.
+    pub fn sqpoll(mut self) -> Self {
+        self.flags |= SetupFlags::SQPOLL;
+        self
+    }
+
+    /// Set how long the SQPOLL thread spins before going to sleep
+    ///
+    /// The kernel works in milliseconds; requires [`sqpoll`](Self::sqpoll).
+    pub fn sqpoll_idle(mut self, idle: Duration) -> Self {
+        self.sq_thread_idle = Some(idle);
+        self
+    }
+
+    /// Pin the SQPOLL thread to a CPU (`IORING_SETUP_SQ_AFF`)
+    ///
+    /// Requires [`sqpoll`](Self::sqpoll).
+    pub fn sqpoll_cpu(mut self, cpu: u32) -> Self {
+        self.flags |= SetupFlags::SQ_AFF;
+        self.sq_thread_cpu = Some(cpu);
+        self
+    }
+
+    /// Share the async worker pool of an existing ring (`IORING_SETUP_ATTACH_WQ`)
+    pub fn attach_wq(mut self, ring: &IoUring) -> Self {
+        self.flags |= SetupFlags::ATTACH_WQ;
+        self.wq_fd = Some(ring.as_raw_fd());
+        self
+    }
+
+    /// Start the ring disabled (`IORING_SETUP_R_DISABLED`)
+    ///
+    /// The ring must be enabled with [`IoUring::enable_rings`] before use.
+    pub fn disabled(mut self) -> Self {
+        self.flags |= SetupFlags::R_DISABLED;
+        self
+    }
+
+    /// Keep submitting after an SQE fails to prepare (`IORING_SETUP_SUBMIT_ALL`)
+    pub fn submit_all(mut self) -> Self {
+        self.flags |= SetupFlags::SUBMIT_ALL;
+        self
+    }
+
+    /// Only run task work on transitions into the kernel (`IORING_SETUP_COOP_TASKRUN`)
+    pub fn coop_taskrun(mut self) -> Self {
+        self.flags |= SetupFlags::COOP_TASKRUN;
+        self
+    }
+
+    /// Flag pending task work in the SQ ring (`IORING_SETUP_TASKRUN_FLAG`)
+    ///
+    /// Requires [`coop_taskrun`](Self::coop_taskrun) or
+    /// [`defer_taskrun`](Self::defer_taskrun).
+    pub fn taskrun_flag(mut self) -> Self {
+        self.flags |= SetupFlags::TASKRUN_FLAG;
+        self
+    }
+
+    /// Promise that only one task submits requests (`IORING_SETUP_SINGLE_ISSUER`)
+    pub fn single_issuer(mut self) -> Self {
+        self.flags |= SetupFlags::SINGLE_ISSUER;
+        self
+    }
+
+    /// Defer task work until completions are waited for (`IORING_SETUP_DEFER_TASKRUN`)
+    ///
+    /// Requires [`single_issuer`](Self::single_issuer).
+    pub fn defer_taskrun(mut self) -> Self {
+        self.flags |= SetupFlags::DEFER_TASKRUN;
+        self
+    }
+
+    /// Use 128-byte SQEs (`IORING_SETUP_SQE128`)
+    pub fn sqe128(mut self) -> Self {
+        self.flags |= SetupFlags::SQE128;
+        self
+    }
+
+    /// Use 32-byte CQEs (`IORING_SETUP_CQE32`)
+    pub fn cqe32(mut self) -> Self {
+        self.flags |= SetupFlags::CQE32;
+        self
+    }
+
+    /// Place the rings in caller-provided memory (`IORING_SETUP_NO_MMAP`)
+    ///
+    /// The memory is owned by the resulting [`IoUring`] and released after
+    /// the ring is torn down.
+    pub fn no_mmap(mut self, memory: RingMemory) -> Self {
+        self.flags |= SetupFlags::NO_MMAP;
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Only hand out a registered index, never a ring fd (`IORING_SETUP_REGISTERED_FD_ONLY`)
+    ///
+    /// Requires [`no_mmap`](Self::no_mmap). As after
+    /// [`IoUring::close_ring_fd`], [`as_raw_fd`](IoUring::as_raw_fd) returns
+    /// -1, and the ring can only be used from the thread that built it.
+    pub fn registered_fd_only(mut self) -> Self {
+        self.flags |= SetupFlags::REGISTERED_FD_ONLY;
+        self
+    }
+
+    /// Drop the SQ index array and submit SQEs in ring order (`IORING_SETUP_NO_SQARRAY`)
+    pub fn no_sqarray(mut self) -> Self {
+        self.flags |= SetupFlags::NO_SQARRAY;
+        self
+    }
+
+    /// Choose what happens when an SQE is requested from a full SQ
+    ///
+    /// Defaults to [`BackpressurePolicy::FailFast`].
+    pub fn backpressure(mut self, policy: BackpressurePolicy) -> Self {
+        self.backpressure = policy;
+        self
+    }
+
+    /// Enable NAPI busy polling once the ring is created
+    ///
+    /// See [`IoUring::register_napi`].
+    pub fn napi(mut self, config: NapiConfig) -> Self {
+        self.napi = Some(config);
+        self
+    }
+
+    /// Check the configuration and create the ring
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Number of submission queue entries (will be rounded up to power of 2)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the options conflict, or
+    /// [`Error::Setup`] if the kernel rejects the ring.
+    pub fn build(self, entries: u32) -> Result<IoUring> {
+        let mut params = self.to_params(entries)?;
+        let mut ring = IoUring::with_params_and_memory(entries, &mut params, self.memory)?;
+        ring.set_backpressure(self.backpressure);
+        if let Some(napi) = &self.napi {
+            ring.register_napi(napi)?;
+        }
+        Ok(ring)
+    }
+
+    fn to_params(&self, entries: u32) -> Result<sys::io_uring_params> {
+        self.validate(entries)?;
+
+        let mut params = sys::io_uring_params {
+            flags: self.flags.bits(),
+            ..Default::default()
+        };
+
+        if let Some(cq_entries) = self.cq_entries {
+            params.cq_entries = cq_entries;
+        }
+        if let Some(idle) = self.sq_thread_idle {
+            params.sq_thread_idle = u32::try_from(idle.as_millis()).map_err(|_| {
+                invalid(
+                    "sq_thread_idle",
+                    "idle time does not fit in u32 milliseconds",
+                )
+            })?;
+        }
+        if let Some(cpu) = self.sq_thread_cpu {
+            params.sq_thread_cpu = cpu;
+        }
+        if let Some(fd) = self.wq_fd {
+            params.wq_fd = fd as u32;
+        }
+
+        Ok(params)
+    }
+
+    fn validate(&self, entries: u32) -> Result<()> {
+        let flags = self.flags;
+
+        if entries == 0 {
+            return Err(invalid("entries", "must be non-zero"));
+        }
+
+        if let Some(cq_entries) = self.cq_entries {
+            if cq_entries < entries {
+                return Err(invalid(
+                    "cq_entries",
+                    format!(
+                        "{} is smaller than the {} submission queue entries",
+                        cq_entries, entries
+                    ),
+                ));
+            }
+        }
+
+        if !flags.contains(SetupFlags::SQPOLL) {
+            if self.sq_thread_idle.is_some() {
+                return Err(invalid("sq_thread_idle", "requires SQPOLL"));
+            }
+            if self.sq_thread_cpu.is_some() {
+                return Err(invalid("sq_thread_cpu", "requires SQPOLL"));
+            }
+        } else if flags.intersects(
+            SetupFlags::COOP_TASKRUN | SetupFlags::TASKRUN_FLAG | SetupFlags::DEFER_TASKRUN,
+        ) {
+            return Err(invalid(
+                "flags",
+                "SQPOLL cannot be combined with COOP_TASKRUN, TASKRUN_FLAG or DEFER_TASKRUN",
+            ));
+        }
+
+        if flags.contains(SetupFlags::DEFER_TASKRUN) && !flags.contains(SetupFlags::SINGLE_ISSUER) {
+            return Err(invalid("flags", "DEFER_TASKRUN requires SINGLE_ISSUER"));
+        }
+
+        if flags.contains(SetupFlags::REGISTERED_FD_ONLY) && !flags.contains(SetupFlags::NO_MMAP) {
+            return Err(invalid("flags", "REGISTERED_FD_ONLY requires NO_MMAP"));
+        }
+
+        if flags.contains(SetupFlags::TASKRUN_FLAG)
+            && !flags.intersects(SetupFlags::COOP_TASKRUN | SetupFlags::DEFER_TASKRUN)
+        {
+            return Err(invalid(
+                "flags",
+                "TASKRUN_FLAG requires COOP_TASKRUN or DEFER_TASKRUN",
+            ));
+        }
+
+        if let Some(napi) = &self.napi {
+            napi.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> Error {
+    Error::InvalidParameter {
+        field,
+        reason: reason.into(),
+    }
+}
+
+/// Memory region backing the rings of an `IORING_SETUP_NO_MMAP` ring
+///
+/// The region must be page aligned and large enough to hold the SQEs and
+/// both rings; liburing reports `ENOMEM` if it is too small.
+#[derive(Debug)]
+pub struct RingMemory {
+    ptr: NonNull<u8>,
+    len: usize,
+    owned: bool,
+}
+
+impl RingMemory {
+    /// Allocate an anonymous, page aligned region of `len` bytes
+    pub fn new(len: usize) -> Result<Self> {
+        if len == 0 {
+            return Err(invalid("memory", "length must be non-zero"));
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+
+        Ok(Self {
+            ptr: NonNull::new(ptr as *mut u8).expect("mmap returned null"),
+            len,
+            owned: true,
+        })
+    }
+
+    /// Wrap an existing region without taking ownership of it
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be page aligned, valid for reads and writes of `len` bytes,
+    /// and stay mapped until the ring using it has been dropped.
+    pub unsafe fn from_raw_parts(ptr: NonNull<u8>, len: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            owned: false,
+        }
+    }
+
+    /// Pointer to the start of the region
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Length of the region in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the region is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for RingMemory {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len);
+            }
+        }
+    }
+}
+
+// The region is plain memory; the ring that uses it is already Send
+unsafe impl Send for RingMemory {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_of(err: Error) -> &'static str {
+        match err {
+            Error::InvalidParameter { field, .. } => field,
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_small_cq() {
+        let err = IoUringBuilder::new().cq_entries(4).validate(8).unwrap_err();
+        assert_eq!(field_of(err), "cq_entries");
+    }
+
+    #[test]
+    fn test_rejects_sqpoll_options_without_sqpoll() {
+        let err = IoUringBuilder::new()
+            .sqpoll_idle(Duration::from_millis(10))
+            .validate(8)
+            .unwrap_err();
+        assert_eq!(field_of(err), "sq_thread_idle");
+
+        let err = IoUringBuilder::new().sqpoll_cpu(0).validate(8).unwrap_err();
+        assert_eq!(field_of(err), "sq_thread_cpu");
+    }
+
+    #[test]
+    fn test_rejects_incompatible_flags() {
+        let err = IoUringBuilder::new()
+            .defer_taskrun()
+            .validate(8)
+            .unwrap_err();
+        assert_eq!(field_of(err), "flags");
+
+        let err = IoUringBuilder::new()
+            .sqpoll()
+            .coop_taskrun()
+            .validate(8)
+            .unwrap_err();
+        assert_eq!(field_of(err), "flags");
+
+        let err = IoUringBuilder::new()
+            .taskrun_flag()
+            .validate(8)
+            .unwrap_err();
+        assert_eq!(field_of(err), "flags");
+    }
+
+    #[test]
+    fn test_params_from_options() {
+        let params = IoUringBuilder::new()
+            .sqpoll()
+            .sqpoll_idle(Duration::from_millis(250))
+            .sqpoll_cpu(1)
+            .cq_entries(64)
+            .to_params(16)
+            .unwrap();
+
+        assert_eq!(params.sq_thread_idle, 250);
+        assert_eq!(params.sq_thread_cpu, 1);
+        assert_eq!(params.cq_entries, 64);
+        assert_eq!(
+            params.flags,
+            (SetupFlags::SQPOLL | SetupFlags::SQ_AFF | SetupFlags::CQSIZE).bits()
+        );
+    }
+}
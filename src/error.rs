@@ -24,6 +24,14 @@ pub enum Error {
     /// Invalid operation or parameter
     InvalidOperation(String),
 
+    /// A setup parameter was rejected before calling into the kernel
+    InvalidParameter {
+        /// Name of the offending `io_uring_params` field or builder option
+        field: &'static str,
+        /// Why the value was rejected
+        reason: String,
+    },
+
     /// Feature not supported by the kernel
     NotSupported(String),
 }
@@ -36,6 +44,9 @@ impl fmt::Display for Error {
             Error::SubmissionQueueFull => write!(f, "submission queue is full"),
             Error::CompletionQueueEmpty => write!(f, "no completion queue entries available"),
             Error::InvalidOperation(msg) => write!(f, "invalid operation: {}", msg),
+            Error::InvalidParameter { field, reason } => {
+                write!(f, "invalid parameter `{}`: {}", field, reason)
+            }
             Error::NotSupported(msg) => write!(f, "feature not supported: {}", msg),
         }
     }
@@ -56,6 +67,15 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(e) | Error::Setup(e) => e,
+            other => io::Error::new(io::ErrorKind::Other, other),
+        }
+    }
+}
+
 /// Convert a negative return code to an io::Error
 pub(crate) fn from_ret_code(ret: i32) -> io::Error {
     io::Error::from_raw_os_error(-ret)
@@ -30,16 +30,34 @@
 
 pub mod sys;
 
+mod builder;
 mod error;
+mod napi;
 pub mod ops;
 mod queue;
+mod register;
+mod restrictions;
+mod split;
+mod stats;
+pub mod types;
 mod uring;
 
-#[cfg(any(feature = "async-tokio", feature = "async-async-std"))]
+#[cfg(feature = "async")]
 pub mod async_io;
 
+#[cfg(feature = "runtime")]
+pub mod runtime;
+
+pub use builder::{IoUringBuilder, RingMemory};
 pub use error::{Error, Result};
-pub use queue::{CompletionQueue, Cqe, SubmissionQueue};
+pub use napi::{NapiConfig, NapiTracking};
+pub use queue::{
+    BackpressurePolicy, Completion, CompletionQueue, Completions, Cqe, Sqe128, SubmissionQueue,
+    SQE128_CMD_LEN,
+};
+pub use restrictions::Restrictions;
+pub use split::{Completer, LocalSubmitter, Submitter};
+pub use stats::{FdInfo, RingStats};
 pub use uring::IoUring;
 
 // Re-export key types that users might need
@@ -51,7 +69,7 @@ pub mod flags {
 
     bitflags! {
         /// Flags for io_uring_setup
-        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
         pub struct SetupFlags: u32 {
             /// Perform busy-waiting for I/O completion
             const IOPOLL = crate::sys::IORING_SETUP_IOPOLL;
@@ -81,12 +99,18 @@ pub mod flags {
             const SINGLE_ISSUER = crate::sys::IORING_SETUP_SINGLE_ISSUER;
             /// Defer running task work
             const DEFER_TASKRUN = crate::sys::IORING_SETUP_DEFER_TASKRUN;
+            /// Use application-provided memory for the rings
+            const NO_MMAP = crate::sys::IORING_SETUP_NO_MMAP;
+            /// Only allow the ring to be used through a registered fd
+            const REGISTERED_FD_ONLY = crate::sys::IORING_SETUP_REGISTERED_FD_ONLY;
+            /// Don't use the SQ index array
+            const NO_SQARRAY = crate::sys::IORING_SETUP_NO_SQARRAY;
         }
     }
 
     bitflags! {
         /// Flags for submission queue entries
-        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
         pub struct SqeFlags: u8 {
             /// Use fixed file descriptor
             const FIXED_FILE = 1 << 0;
@@ -104,6 +128,79 @@ pub mod flags {
             const CQE_SKIP_SUCCESS = 1 << 6;
         }
     }
+
+    bitflags! {
+        /// Flags the kernel sets on the submission queue ring
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct SqFlags: u32 {
+            /// The SQPOLL thread is asleep and needs a wakeup
+            const NEED_WAKEUP = crate::sys::IORING_SQ_NEED_WAKEUP;
+            /// Completions are waiting in the kernel's overflow list
+            const CQ_OVERFLOW = crate::sys::IORING_SQ_CQ_OVERFLOW;
+            /// Task work is pending
+            const TASKRUN = crate::sys::IORING_SQ_TASKRUN;
+        }
+    }
+
+    bitflags! {
+        /// Features reported by the kernel at setup time
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Features: u32 {
+            /// SQ and CQ rings share a single mmap
+            const SINGLE_MMAP = crate::sys::IORING_FEAT_SINGLE_MMAP;
+            /// CQEs are not dropped when the CQ overflows
+            const NODROP = crate::sys::IORING_FEAT_NODROP;
+            /// SQE data is stable once submitted
+            const SUBMIT_STABLE = crate::sys::IORING_FEAT_SUBMIT_STABLE;
+            /// Offset -1 means the current file position
+            const RW_CUR_POS = crate::sys::IORING_FEAT_RW_CUR_POS;
+            /// Requests use the submitter's credentials
+            const CUR_PERSONALITY = crate::sys::IORING_FEAT_CUR_PERSONALITY;
+            /// Internal polling for retries instead of worker threads
+            const FAST_POLL = crate::sys::IORING_FEAT_FAST_POLL;
+            /// 32-bit poll masks
+            const POLL_32BITS = crate::sys::IORING_FEAT_POLL_32BITS;
+            /// SQPOLL works without fixed files
+            const SQPOLL_NONFIXED = crate::sys::IORING_FEAT_SQPOLL_NONFIXED;
+            /// `io_uring_enter` takes extended arguments (timeouts)
+            const EXT_ARG = crate::sys::IORING_FEAT_EXT_ARG;
+            /// io-wq workers are native threads
+            const NATIVE_WORKERS = crate::sys::IORING_FEAT_NATIVE_WORKERS;
+            /// Registered resources can be tagged
+            const RSRC_TAGS = crate::sys::IORING_FEAT_RSRC_TAGS;
+            /// `CQE_SKIP_SUCCESS` is supported
+            const CQE_SKIP = crate::sys::IORING_FEAT_CQE_SKIP;
+            /// Linked requests resolve files at execution time
+            const LINKED_FILE = crate::sys::IORING_FEAT_LINKED_FILE;
+            /// The ring fd can be registered with the ring
+            const REG_REG_RING = crate::sys::IORING_FEAT_REG_REG_RING;
+            /// Bundled send/recv is supported
+            const RECVSEND_BUNDLE = crate::sys::IORING_FEAT_RECVSEND_BUNDLE;
+            /// Waits accept a minimum batch timeout
+            const MIN_TIMEOUT = crate::sys::IORING_FEAT_MIN_TIMEOUT;
+            /// Read/write attributes are supported
+            const RW_ATTR = crate::sys::IORING_FEAT_RW_ATTR;
+            /// Waits can skip iowait accounting
+            const NO_IOWAIT = crate::sys::IORING_FEAT_NO_IOWAIT;
+        }
+    }
+
+    bitflags! {
+        /// Flags for completion queue entries
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct CqeFlags: u32 {
+            /// Upper 16 bits hold the selected buffer ID
+            const BUFFER = crate::sys::IORING_CQE_F_BUFFER;
+            /// More completions will follow for this request
+            const MORE = crate::sys::IORING_CQE_F_MORE;
+            /// Socket still has data after this receive
+            const SOCK_NONEMPTY = crate::sys::IORING_CQE_F_SOCK_NONEMPTY;
+            /// Zero-copy send notification
+            const NOTIF = crate::sys::IORING_CQE_F_NOTIF;
+            /// Provided buffer will be used again by the request
+            const BUF_MORE = crate::sys::IORING_CQE_F_BUF_MORE;
+        }
+    }
 }
 
 /// io_uring operation codes
@@ -0,0 +1,185 @@
+//! NAPI busy polling
+
+use crate::error::{check_ret, Error, Result};
+use crate::sys;
+use crate::uring::IoUring;
+use std::time::Duration;
+
+/// How the ring finds the NAPI instances to busy poll
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NapiTracking {
+    /// Track the NAPI ids of sockets the ring receives on
+    #[default]
+    Dynamic,
+    /// Only poll ids added explicitly (Linux 6.13)
+    Static,
+}
+
+/// NAPI busy poll settings for a ring
+///
+/// While waiting for completions the ring busy polls the tracked NAPI
+/// instances for up to the busy poll timeout before sleeping, like
+/// `SO_BUSY_POLL` does for epoll. Needs Linux 6.9.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use liburing_rs::{IoUring, NapiConfig};
+///
+/// let ring = IoUring::builder()
+///     .napi(NapiConfig::new(Duration::from_micros(50)).prefer_busy_poll(true))
+///     .build(64)?;
+/// # Ok::<(), liburing_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NapiConfig {
+    busy_poll_timeout: Duration,
+    prefer_busy_poll: bool,
+    tracking: NapiTracking,
+    ids: Vec<u32>,
+}
+
+impl NapiConfig {
+    /// Busy poll for up to `busy_poll_timeout`, with microsecond precision
+    pub fn new(busy_poll_timeout: Duration) -> Self {
+        Self {
+            busy_poll_timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Set `SO_PREFER_BUSY_POLL` semantics, deferring softirq processing
+    /// while the ring is busy polling
+    pub fn prefer_busy_poll(mut self, prefer: bool) -> Self {
+        self.prefer_busy_poll = prefer;
+        self
+    }
+
+    /// Choose how NAPI instances are tracked
+    pub fn tracking(mut self, tracking: NapiTracking) -> Self {
+        self.tracking = tracking;
+        self
+    }
+
+    /// Add a NAPI id to poll, as reported by `SO_INCOMING_NAPI_ID`
+    ///
+    /// Only valid with [`NapiTracking::Static`].
+    pub fn add_id(mut self, id: u32) -> Self {
+        if !self.ids.contains(&id) {
+            self.ids.push(id);
+        }
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if u32::try_from(self.busy_poll_timeout.as_micros()).is_err() {
+            return Err(Error::InvalidParameter {
+                field: "busy_poll_timeout",
+                reason: "timeout does not fit in u32 microseconds".to_string(),
+            });
+        }
+        if !self.ids.is_empty() && self.tracking != NapiTracking::Static {
+            return Err(Error::InvalidParameter {
+                field: "napi_ids",
+                reason: "NAPI ids can only be added with static tracking".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn to_raw(&self) -> sys::io_uring_napi {
+        let tracking = match self.tracking {
+            NapiTracking::Dynamic => {
+                sys::io_uring_napi_tracking_strategy_IO_URING_NAPI_TRACKING_DYNAMIC
+            }
+            NapiTracking::Static => {
+                sys::io_uring_napi_tracking_strategy_IO_URING_NAPI_TRACKING_STATIC
+            }
+        };
+        sys::io_uring_napi {
+            busy_poll_to: self.busy_poll_timeout.as_micros() as u32,
+            prefer_busy_poll: self.prefer_busy_poll.into(),
+            opcode: sys::io_uring_napi_op_IO_URING_NAPI_REGISTER_OP as u8,
+            op_param: tracking,
+            ..Default::default()
+        }
+    }
+}
+
+impl IoUring {
+    /// Enable NAPI busy polling on the ring
+    ///
+    /// Replaces any earlier settings. Ids in `config` are added one by one
+    /// after the settings are applied.
+    pub fn register_napi(&mut self, config: &NapiConfig) -> Result<()> {
+        config.validate()?;
+
+        let mut napi = config.to_raw();
+        let ret = unsafe { sys::io_uring_register_napi(self.as_raw_mut(), &mut napi) };
+        check_ret(ret)?;
+
+        for &id in &config.ids {
+            self.add_napi_id(id)?;
+        }
+        Ok(())
+    }
+
+    /// Disable NAPI busy polling
+    ///
+    /// Returns the busy poll timeout and preference that were in effect, as
+    /// reported by the kernel; tracking and ids are not reported.
+    pub fn unregister_napi(&mut self) -> Result<NapiConfig> {
+        let mut napi = sys::io_uring_napi::default();
+        let ret = unsafe { sys::io_uring_unregister_napi(self.as_raw_mut(), &mut napi) };
+        check_ret(ret)?;
+        Ok(
+            NapiConfig::new(Duration::from_micros(napi.busy_poll_to.into()))
+                .prefer_busy_poll(napi.prefer_busy_poll != 0),
+        )
+    }
+
+    /// Start polling a NAPI id on a ring registered with static tracking
+    pub fn add_napi_id(&mut self, id: u32) -> Result<()> {
+        self.update_napi_id(sys::io_uring_napi_op_IO_URING_NAPI_STATIC_ADD_ID, id)
+    }
+
+    /// Stop polling a NAPI id added with [`add_napi_id`](Self::add_napi_id)
+    pub fn remove_napi_id(&mut self, id: u32) -> Result<()> {
+        self.update_napi_id(sys::io_uring_napi_op_IO_URING_NAPI_STATIC_DEL_ID, id)
+    }
+
+    fn update_napi_id(&mut self, op: sys::io_uring_napi_op, id: u32) -> Result<()> {
+        let mut napi = sys::io_uring_napi {
+            opcode: op as u8,
+            op_param: id,
+            ..Default::default()
+        };
+        let ret = unsafe { sys::io_uring_register_napi(self.as_raw_mut(), &mut napi) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_napi_config() {
+        let raw = NapiConfig::new(Duration::from_micros(50))
+            .prefer_busy_poll(true)
+            .tracking(NapiTracking::Static)
+            .to_raw();
+        assert_eq!(raw.busy_poll_to, 50);
+        assert_eq!(raw.prefer_busy_poll, 1);
+        assert_eq!(
+            raw.op_param,
+            sys::io_uring_napi_tracking_strategy_IO_URING_NAPI_TRACKING_STATIC
+        );
+
+        assert!(NapiConfig::new(Duration::from_secs(1 << 40))
+            .validate()
+            .is_err());
+        assert!(NapiConfig::default().add_id(1).validate().is_err());
+    }
+}
@@ -3,7 +3,9 @@
 //! This module provides safe wrappers around io_uring operation preparation functions.
 
 use crate::sys;
+use crate::types::{monotonic_timespec, timespec};
 use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
 
 /// Helper trait for preparing operations on SQEs
 pub trait PrepareOp {
@@ -49,6 +51,11 @@ impl Read {
     }
 }
 
+// SAFETY: `Read` never dereferences `buf`; only the kernel writes through
+// it, whichever thread submits the SQE. `new` makes the caller keep the
+// buffer alive and untouched until the CQE arrives.
+unsafe impl Send for Read {}
+
 impl PrepareOp for Read {
     fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
         unsafe {
@@ -101,6 +108,11 @@ impl Write {
     }
 }
 
+// SAFETY: `buf` is only read, and only by the kernel; `Write` itself never
+// dereferences it. Sending the op to the submitting thread gives no thread
+// access to the buffer that `new`'s contract doesn't already cover.
+unsafe impl Send for Write {}
+
 impl PrepareOp for Write {
     fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
         unsafe {
@@ -190,6 +202,11 @@ impl Accept {
     }
 }
 
+// SAFETY: `addr` and `addrlen` are either null or written by the kernel
+// when a connection is accepted; `Accept` never dereferences them, and
+// `new` requires both to stay valid until the CQE arrives.
+unsafe impl Send for Accept {}
+
 impl PrepareOp for Accept {
     fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
         unsafe {
@@ -219,6 +236,11 @@ impl Connect {
     }
 }
 
+// SAFETY: the kernel only reads the `sockaddr` behind `addr`, and `Connect`
+// never dereferences it, so the op can move to whichever thread submits it
+// as long as `new`'s promise to keep the address alive holds.
+unsafe impl Send for Connect {}
+
 impl PrepareOp for Connect {
     fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
         unsafe {
@@ -248,6 +270,204 @@ impl PrepareOp for Close {
     }
 }
 
+/// Multishot accept operation
+///
+/// Posts one CQE per accepted connection, with the new descriptor as its
+/// result, until it fails or is cancelled.
+pub struct AcceptMultishot {
+    /// Listening socket file descriptor
+    pub fd: RawFd,
+    /// Accept flags, such as `SOCK_CLOEXEC`
+    pub flags: i32,
+}
+
+impl AcceptMultishot {
+    /// Create a new multishot accept operation
+    pub fn new(fd: RawFd, flags: i32) -> Self {
+        Self { fd, flags }
+    }
+}
+
+impl PrepareOp for AcceptMultishot {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_multishot_accept(
+                sqe,
+                self.fd,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                self.flags,
+            );
+        }
+    }
+}
+
+/// Multishot poll operation
+///
+/// Posts one CQE with the ready events each time the file becomes ready,
+/// until it is removed or cancelled.
+pub struct PollMultishot {
+    /// File descriptor to poll
+    pub fd: RawFd,
+    /// Events to wait for, such as `POLLIN`
+    pub poll_mask: u32,
+}
+
+impl PollMultishot {
+    /// Create a new multishot poll operation
+    pub fn new(fd: RawFd, poll_mask: u32) -> Self {
+        Self { fd, poll_mask }
+    }
+}
+
+impl PrepareOp for PollMultishot {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_poll_multishot(sqe, self.fd, self.poll_mask);
+        }
+    }
+}
+
+/// Timeout operation
+///
+/// Completes with `-ETIME` once the duration has passed. The kernel copies
+/// the timespec when the SQE is submitted, so the op has to stay in place
+/// until then.
+pub struct Timeout {
+    /// Time to wait
+    pub ts: sys::__kernel_timespec,
+    /// Completions to wait for instead, or the number of expirations of a
+    /// multishot timeout; 0 means no limit
+    pub count: u32,
+    /// Timeout flags, such as `IORING_TIMEOUT_ABS`
+    pub flags: u32,
+}
+
+impl Timeout {
+    /// Create a timeout that fires once after `duration`
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            ts: timespec(duration),
+            count: 0,
+            flags: 0,
+        }
+    }
+
+    /// Create a timeout that fires at `deadline`, with `IORING_TIMEOUT_ABS`
+    ///
+    /// The deadline is measured on `CLOCK_MONOTONIC`, so it holds however
+    /// late the SQE is submitted.
+    pub fn at(deadline: Instant) -> Self {
+        Self {
+            ts: monotonic_timespec(deadline),
+            count: 0,
+            flags: sys::IORING_TIMEOUT_ABS,
+        }
+    }
+
+    /// Create a timeout that fires every `interval`, `count` times
+    ///
+    /// Each expiration posts a CQE; a `count` of 0 keeps it firing until it
+    /// is cancelled. Needs Linux 6.4.
+    pub fn multishot(interval: Duration, count: u32) -> Self {
+        Self {
+            ts: timespec(interval),
+            count,
+            flags: sys::IORING_TIMEOUT_MULTISHOT,
+        }
+    }
+}
+
+impl PrepareOp for Timeout {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_timeout(sqe, &self.ts as *const _ as *mut _, self.count, self.flags);
+        }
+    }
+}
+
+/// Linked timeout operation (`IORING_OP_LINK_TIMEOUT`)
+///
+/// Bounds the SQE queued right before it, which must carry `IO_LINK`: if
+/// that op hasn't completed when the duration has passed, it is cancelled
+/// and completes with `-ECANCELED`. The timeout itself completes with
+/// `-ETIME` if it fired, or `-ECANCELED` if the op finished first. Like
+/// [`Timeout`], it has to stay in place until submitted.
+pub struct LinkTimeout {
+    /// Time the linked op may take
+    pub ts: sys::__kernel_timespec,
+    /// Timeout flags, such as `IORING_TIMEOUT_ABS`
+    pub flags: u32,
+}
+
+impl LinkTimeout {
+    /// Create a linked timeout of `duration`
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            ts: timespec(duration),
+            flags: 0,
+        }
+    }
+
+    /// Create a linked timeout that fires at `deadline`, with
+    /// `IORING_TIMEOUT_ABS`
+    pub fn at(deadline: Instant) -> Self {
+        Self {
+            ts: monotonic_timespec(deadline),
+            flags: sys::IORING_TIMEOUT_ABS,
+        }
+    }
+}
+
+impl PrepareOp for LinkTimeout {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_link_timeout(sqe, &self.ts as *const _ as *mut _, self.flags);
+        }
+    }
+}
+
+/// Passthrough command operation (`IORING_OP_URING_CMD`)
+///
+/// The command payload lives in the SQE's `cmd` area. On rings set up with
+/// `SQE128`, prepare this on an SQE from
+/// [`SubmissionQueue::get_sqe128`](crate::SubmissionQueue::get_sqe128) and
+/// write the payload through [`Sqe128::cmd_mut`](crate::Sqe128::cmd_mut).
+pub struct UringCmd {
+    /// File descriptor the command is issued against
+    pub fd: RawFd,
+    /// Driver-specific command opcode
+    pub cmd_op: u32,
+}
+
+impl UringCmd {
+    /// Create a new passthrough command
+    pub fn new(fd: RawFd, cmd_op: u32) -> Self {
+        Self { fd, cmd_op }
+    }
+}
+
+impl PrepareOp for UringCmd {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_rw(
+                sys::io_uring_op::IORING_OP_URING_CMD as i32,
+                sqe,
+                self.fd,
+                std::ptr::null(),
+                0,
+                0,
+            );
+        }
+        sqe.__bindgen_anon_1 = sys::io_uring_sqe__bindgen_ty_1 {
+            __bindgen_anon_1: sys::io_uring_sqe__bindgen_ty_1__bindgen_ty_1 {
+                cmd_op: self.cmd_op,
+                __pad1: 0,
+            },
+        };
+    }
+}
+
 /// Extension methods for io_uring_sqe
 pub trait SqeExt {
     /// Set user data on this SQE
@@ -255,6 +475,11 @@ pub trait SqeExt {
 
     /// Set flags on this SQE
     fn set_flags(&mut self, flags: u8);
+
+    /// Issue this SQE with the credentials of a registered personality
+    ///
+    /// `id` comes from [`IoUring::register_personality`](crate::IoUring::register_personality).
+    fn set_personality(&mut self, id: u16);
 }
 
 impl SqeExt for sys::io_uring_sqe {
@@ -269,4 +494,8 @@ impl SqeExt for sys::io_uring_sqe {
             sys::io_uring_sqe_set_flags(self, flags as u32);
         }
     }
+
+    fn set_personality(&mut self, id: u16) {
+        self.personality = id;
+    }
 }
@@ -1,21 +1,78 @@
 //! Submission and completion queue operations
 
 use crate::error::{Error, Result};
+use crate::flags::{CqeFlags, SqeFlags};
 use crate::sys;
+use crate::types::{sigmask_ptr, timespec, SigSet};
 use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Offset of the `cmd` area inside an SQE
+const SQE_CMD_OFFSET: usize = 48;
+
+/// Size of the `cmd` area of a 128-byte SQE
+pub const SQE128_CMD_LEN: usize = 80;
+
+/// What [`SubmissionQueue::get_sqe_or_err`] does when the SQ is full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Return [`Error::SubmissionQueueFull`] straight away
+    #[default]
+    FailFast,
+    /// Submit the queued entries to the kernel and try again once
+    ///
+    /// A link chain is never split: if the last queued SQE carries
+    /// `IO_LINK` or `IO_HARDLINK`, nothing is submitted and
+    /// [`Error::SubmissionQueueFull`] is returned as with `FailFast`.
+    FlushAndRetry,
+    /// On SQPOLL rings, like `FlushAndRetry` but also wait for the poller
+    /// thread to free a slot; on other rings, the same as `FlushAndRetry`
+    ///
+    /// Without SQPOLL the kernel consumes SQEs while submitting them, so
+    /// there is never anything to wait for. Completions aren't waited for
+    /// either: if the flush fails with `EBUSY` because the CQ is
+    /// overflowing, the error is returned.
+    Block,
+}
 
 /// Submission queue for io_uring
 ///
 /// Used to obtain submission queue entries (SQEs) and submit them to the kernel.
 pub struct SubmissionQueue<'ring> {
     ring: *mut sys::io_uring,
+    policy: BackpressurePolicy,
+    wakeups: &'ring AtomicU64,
     _phantom: PhantomData<&'ring mut sys::io_uring>,
 }
 
 impl<'ring> SubmissionQueue<'ring> {
-    pub(crate) fn new(ring: &'ring mut sys::io_uring) -> Self {
+    pub(crate) fn new(
+        ring: &'ring mut sys::io_uring,
+        policy: BackpressurePolicy,
+        wakeups: &'ring AtomicU64,
+    ) -> Self {
+        Self {
+            ring,
+            policy,
+            wakeups,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `ring` must stay valid for `'ring` and nothing else may touch the SQ.
+    pub(crate) unsafe fn from_raw(
+        ring: *mut sys::io_uring,
+        policy: BackpressurePolicy,
+        wakeups: &'ring AtomicU64,
+    ) -> Self {
         Self {
             ring,
+            policy,
+            wakeups,
             _phantom: PhantomData,
         }
     }
@@ -34,14 +91,104 @@ impl<'ring> SubmissionQueue<'ring> {
     }
 
     /// Get the next available SQE or return an error if full
+    ///
+    /// When the queue is full, the ring's [`BackpressurePolicy`] decides
+    /// whether to flush queued entries to the kernel before giving up.
     pub fn get_sqe_or_err(&mut self) -> Result<&mut sys::io_uring_sqe> {
+        if self.is_full() {
+            self.make_room()?;
+        }
         self.get_sqe().ok_or(Error::SubmissionQueueFull)
     }
 
+    fn make_room(&mut self) -> Result<()> {
+        // Flushing now would submit the head of the chain without its tail
+        if self.ends_in_link() {
+            return Err(Error::SubmissionQueueFull);
+        }
+
+        match self.policy {
+            BackpressurePolicy::FailFast => Err(Error::SubmissionQueueFull),
+            BackpressurePolicy::FlushAndRetry => self.flush(),
+            BackpressurePolicy::Block => {
+                self.flush()?;
+
+                // Only an SQPOLL thread can still be holding slots here
+                let sqpoll = unsafe { (*self.ring).flags } & sys::IORING_SETUP_SQPOLL != 0;
+                while sqpoll && self.is_full() {
+                    let ret = unsafe { sys::io_uring_sqring_wait(self.ring) };
+                    if ret < 0 {
+                        return Err(crate::error::from_ret_code(ret).into());
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the last SQE not yet handed to the kernel links to the next
+    fn ends_in_link(&self) -> bool {
+        unsafe {
+            let ring = &*self.ring;
+            if ring.sq.sqe_tail == ring.sq.sqe_head {
+                return false;
+            }
+            let shift = if ring.flags & sys::IORING_SETUP_SQE128 != 0 {
+                1
+            } else {
+                0
+            };
+            let index = (ring.sq.sqe_tail.wrapping_sub(1) & ring.sq.ring_mask) << shift;
+            let flags = SqeFlags::from_bits_retain((*ring.sq.sqes.add(index as usize)).flags);
+            flags.intersects(SqeFlags::IO_LINK | SqeFlags::IO_HARDLINK)
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // With IORING_FEAT_NODROP the kernel keeps CQEs that didn't fit in
+        // an overflow list, and refuses submissions with EBUSY while it is
+        // not empty; move them into the CQ first. Older kernels drop such
+        // CQEs instead and never refuse.
+        if unsafe { sys::io_uring_cq_has_overflow(self.ring) } {
+            let ret = unsafe { sys::io_uring_get_events(self.ring) };
+            if ret < 0 {
+                return Err(crate::error::from_ret_code(ret).into());
+            }
+        }
+
+        self.submit().map(|_| ())
+    }
+
+    /// Get the next available 128-byte SQE
+    ///
+    /// Only available on rings set up with `SQE128`. The extra 64 bytes are
+    /// zeroed; prepare the operation first and fill in the command payload
+    /// through [`Sqe128::cmd_mut`] afterwards.
+    pub fn get_sqe128(&mut self) -> Result<Sqe128<'_>> {
+        let is_big = unsafe { (*self.ring).flags } & sys::IORING_SETUP_SQE128 != 0;
+        if !is_big {
+            return Err(Error::NotSupported(
+                "ring was not set up with SQE128".to_string(),
+            ));
+        }
+
+        let sqe = self.get_sqe().ok_or(Error::SubmissionQueueFull)?;
+        unsafe {
+            let extra = (sqe as *mut sys::io_uring_sqe as *mut u8)
+                .add(std::mem::size_of::<sys::io_uring_sqe>());
+            std::ptr::write_bytes(extra, 0, std::mem::size_of::<sys::io_uring_sqe>());
+        }
+
+        Ok(Sqe128 { sqe })
+    }
+
     /// Submit all pending SQEs to the kernel
     ///
-    /// Returns the number of SQEs submitted.
+    /// Returns the number of SQEs submitted. On SQPOLL rings no system call
+    /// is made while the poller thread is awake; it is only woken up with
+    /// `IORING_ENTER_SQ_WAKEUP` once it has gone idle.
     pub fn submit(&mut self) -> Result<usize> {
+        note_sqpoll_wakeup(self.ring, self.wakeups);
         let ret = unsafe { sys::io_uring_submit(self.ring) };
 
         if ret < 0 {
@@ -53,6 +200,7 @@ impl<'ring> SubmissionQueue<'ring> {
 
     /// Submit all pending SQEs and wait for at least `wait_nr` completions
     pub fn submit_and_wait(&mut self, wait_nr: u32) -> Result<usize> {
+        note_sqpoll_wakeup(self.ring, self.wakeups);
         let ret = unsafe { sys::io_uring_submit_and_wait(self.ring, wait_nr) };
 
         if ret < 0 {
@@ -78,11 +226,77 @@ impl<'ring> SubmissionQueue<'ring> {
     }
 }
 
+/// Count the wakeup liburing is about to issue if the SQPOLL thread is idle
+///
+/// liburing makes the same check after publishing the SQ tail, so a poller
+/// that goes to sleep in between is woken but not counted. Like liburing,
+/// nothing is counted when no SQE is waiting for the poller.
+pub(crate) fn note_sqpoll_wakeup(ring: *const sys::io_uring, wakeups: &AtomicU64) {
+    unsafe {
+        if (*ring).flags & sys::IORING_SETUP_SQPOLL == 0 {
+            return;
+        }
+
+        let head = (*((*ring).sq.khead as *const AtomicU32)).load(Ordering::Acquire);
+        if (*ring).sq.sqe_tail == head {
+            return;
+        }
+
+        // Pairs with the barrier the poller issues before setting the flag
+        fence(Ordering::SeqCst);
+        let flags = (*((*ring).sq.kflags as *const AtomicU32)).load(Ordering::Relaxed);
+        if flags & sys::IORING_SQ_NEED_WAKEUP != 0 {
+            wakeups.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A 128-byte submission queue entry
+///
+/// Dereferences to the regular `io_uring_sqe` for the common fields and
+/// exposes the 80-byte `cmd` area used by `IORING_OP_URING_CMD`.
+pub struct Sqe128<'a> {
+    sqe: &'a mut sys::io_uring_sqe,
+}
+
+impl Sqe128<'_> {
+    /// Get the command payload
+    pub fn cmd(&self) -> &[u8; SQE128_CMD_LEN] {
+        unsafe { &*(self.cmd_ptr() as *const [u8; SQE128_CMD_LEN]) }
+    }
+
+    /// Get the command payload for writing
+    pub fn cmd_mut(&mut self) -> &mut [u8; SQE128_CMD_LEN] {
+        unsafe { &mut *(self.cmd_ptr() as *mut [u8; SQE128_CMD_LEN]) }
+    }
+
+    fn cmd_ptr(&self) -> *mut u8 {
+        // The slot behind the SQE is 128 bytes long on SQE128 rings
+        unsafe { (self.sqe as *const sys::io_uring_sqe as *mut u8).add(SQE_CMD_OFFSET) }
+    }
+}
+
+impl Deref for Sqe128<'_> {
+    type Target = sys::io_uring_sqe;
+
+    fn deref(&self) -> &Self::Target {
+        self.sqe
+    }
+}
+
+impl DerefMut for Sqe128<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.sqe
+    }
+}
+
 /// Completion queue for io_uring
 ///
 /// Used to retrieve and process completion queue entries (CQEs).
 pub struct CompletionQueue<'ring> {
     ring: *mut sys::io_uring,
+    // Set when the SQ is driven from another thread
+    shared: bool,
     _phantom: PhantomData<&'ring mut sys::io_uring>,
 }
 
@@ -90,10 +304,34 @@ impl<'ring> CompletionQueue<'ring> {
     pub(crate) fn new(ring: &'ring mut sys::io_uring) -> Self {
         Self {
             ring,
+            shared: false,
             _phantom: PhantomData,
         }
     }
 
+    /// # Safety
+    ///
+    /// `ring` must stay valid for `'ring` and nothing else may touch the CQ.
+    pub(crate) unsafe fn from_raw_shared(ring: *mut sys::io_uring) -> Self {
+        Self {
+            ring,
+            shared: true,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn check_timed_wait(&self) -> Result<()> {
+        // Without EXT_ARG, liburing implements timed waits by queueing a
+        // timeout SQE, which would race with the submitter
+        let ext_arg = unsafe { (*self.ring).features } & sys::IORING_FEAT_EXT_ARG != 0;
+        if self.shared && !ext_arg {
+            return Err(Error::NotSupported(
+                "timed waits on a split ring need IORING_FEAT_EXT_ARG".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Wait for a completion queue entry
     ///
     /// This blocks until at least one CQE is available.
@@ -116,6 +354,45 @@ impl<'ring> CompletionQueue<'ring> {
         }
     }
 
+    /// Wait for a completion queue entry, giving up after `timeout`
+    ///
+    /// Returns `None` if the timeout expired before a CQE arrived.
+    pub fn wait_cqe_timeout(&mut self, timeout: Duration) -> Result<Option<Cqe<'_>>> {
+        self.check_timed_wait()?;
+
+        let mut cqe: *mut sys::io_uring_cqe = std::ptr::null_mut();
+        let mut ts = timespec(timeout);
+
+        let ret = unsafe { sys::io_uring_wait_cqe_timeout(self.ring, &mut cqe, &mut ts) };
+
+        Cqe::from_wait(ret, cqe, self.ring)
+    }
+
+    /// Wait until `wait_nr` completion queue entries are available
+    ///
+    /// Returns the first entry, or `None` if `timeout` expired first. While
+    /// waiting, `sigmask` replaces the thread's signal mask.
+    pub fn wait_cqes(
+        &mut self,
+        wait_nr: u32,
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> Result<Option<Cqe<'_>>> {
+        if timeout.is_some() {
+            self.check_timed_wait()?;
+        }
+
+        let mut cqe: *mut sys::io_uring_cqe = std::ptr::null_mut();
+        let mut ts = timeout.map(timespec);
+        let ts_ptr = ts.as_mut().map_or(std::ptr::null_mut(), |ts| ts as *mut _);
+
+        let ret = unsafe {
+            sys::io_uring_wait_cqes(self.ring, &mut cqe, wait_nr, ts_ptr, sigmask_ptr(sigmask))
+        };
+
+        Cqe::from_wait(ret, cqe, self.ring)
+    }
+
     /// Peek at a completion queue entry without blocking
     ///
     /// Returns `None` if no CQEs are available.
@@ -135,9 +412,46 @@ impl<'ring> CompletionQueue<'ring> {
         }
     }
 
+    /// Iterate over the completions that are ready right now
+    ///
+    /// Each entry is copied out as a [`Completion`]. The CQ head is advanced
+    /// once, by the number of entries yielded, when the iterator is dropped;
+    /// entries left unvisited stay in the queue.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use liburing_rs::IoUring;
+    ///
+    /// let mut ring = IoUring::new(32)?;
+    /// ring.submit_and_wait(1)?;
+    ///
+    /// for completion in ring.completion().iter() {
+    ///     println!("{} -> {}", completion.user_data(), completion.result());
+    /// }
+    /// # Ok::<(), liburing_rs::Error>(())
+    /// ```
+    pub fn iter(&mut self) -> Completions<'_> {
+        Completions::new(self.ring, false)
+    }
+
+    /// Consume completions until the queue is empty
+    ///
+    /// Like [`CompletionQueue::iter`], but the tail is re-read once the
+    /// initial batch is exhausted, so entries posted while draining are
+    /// picked up too. Completions held in the kernel's overflow list are
+    /// flushed into the CQ as room frees up.
+    pub fn drain(&mut self) -> Completions<'_> {
+        Completions::new(self.ring, true)
+    }
+
     /// Peek at multiple CQEs at once
     ///
     /// Returns the number of CQEs peeked (up to `count`).
+    #[deprecated(
+        since = "0.2.0",
+        note = "use `CompletionQueue::iter` or `CompletionQueue::drain` instead"
+    )]
     pub fn peek_batch(&mut self, cqes: &mut [*mut sys::io_uring_cqe]) -> usize {
         let count = cqes.len() as u32;
 
@@ -145,6 +459,209 @@ impl<'ring> CompletionQueue<'ring> {
     }
 }
 
+/// Batch iterator over ready completions
+///
+/// Created by [`CompletionQueue::iter`] and [`CompletionQueue::drain`].
+pub struct Completions<'a> {
+    ring: *mut sys::io_uring,
+    head: u32,
+    tail: u32,
+    seen: u32,
+    refresh: bool,
+    _phantom: PhantomData<&'a mut sys::io_uring>,
+}
+
+impl Completions<'_> {
+    fn new(ring: *mut sys::io_uring, refresh: bool) -> Self {
+        let head = unsafe { *(*ring).cq.khead };
+        let mut iter = Self {
+            ring,
+            head,
+            tail: head,
+            seen: 0,
+            refresh,
+            _phantom: PhantomData,
+        };
+        iter.load_tail();
+        iter
+    }
+
+    /// Move overflowed CQEs into the CQ, returning true if there were any
+    fn flush_overflow(&mut self) -> bool {
+        if self.seen > 0 {
+            // Hand the consumed slots back so the kernel has room
+            unsafe { sys::io_uring_cq_advance(self.ring, self.seen) };
+            self.seen = 0;
+        }
+
+        unsafe {
+            if !sys::io_uring_cq_has_overflow(self.ring) {
+                return false;
+            }
+            sys::io_uring_get_events(self.ring) >= 0
+        }
+    }
+
+    fn load_tail(&mut self) {
+        // Pairs with the kernel's release store of the tail
+        self.tail =
+            unsafe { (*((*self.ring).cq.ktail as *const AtomicU32)).load(Ordering::Acquire) };
+    }
+}
+
+impl Iterator for Completions<'_> {
+    type Item = Completion;
+
+    fn next(&mut self) -> Option<Completion> {
+        if self.head == self.tail {
+            if !self.refresh {
+                return None;
+            }
+            self.load_tail();
+            if self.head == self.tail && self.flush_overflow() {
+                self.load_tail();
+            }
+            if self.head == self.tail {
+                return None;
+            }
+        }
+
+        let completion = unsafe {
+            let ring = &*self.ring;
+            let big = ring.flags & sys::IORING_SETUP_CQE32 != 0;
+            let shift = if big { 1 } else { 0 };
+            let index = ((self.head & ring.cq.ring_mask) << shift) as usize;
+            Completion::from_raw(ring.cq.cqes.add(index), big)
+        };
+
+        self.head = self.head.wrapping_add(1);
+        self.seen += 1;
+        Some(completion)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let ready = self.tail.wrapping_sub(self.head) as usize;
+        if self.refresh {
+            (ready, None)
+        } else {
+            (ready, Some(ready))
+        }
+    }
+}
+
+impl Drop for Completions<'_> {
+    fn drop(&mut self) {
+        if self.seen > 0 {
+            unsafe {
+                sys::io_uring_cq_advance(self.ring, self.seen);
+            }
+        }
+    }
+}
+
+/// An owned copy of a completion queue entry
+///
+/// Unlike [`Cqe`], this does not borrow the ring, so it stays valid once
+/// the slot it was copied from is handed back to the kernel. [`Completions`]
+/// hands its slots back when it is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completion {
+    user_data: u64,
+    result: i32,
+    flags: u32,
+    big_cqe: Option<[u64; 2]>,
+}
+
+impl Completion {
+    unsafe fn from_raw(cqe: *const sys::io_uring_cqe, big: bool) -> Self {
+        let big_cqe = if big {
+            let extra = (cqe as *const u8).add(std::mem::size_of::<sys::io_uring_cqe>());
+            Some(*(extra as *const [u64; 2]))
+        } else {
+            None
+        };
+
+        Self {
+            user_data: (*cqe).user_data,
+            result: (*cqe).res,
+            flags: (*cqe).flags,
+            big_cqe,
+        }
+    }
+
+    /// Get the user data that was set on the SQE
+    pub fn user_data(&self) -> u64 {
+        self.user_data
+    }
+
+    /// Get the result code for this operation
+    ///
+    /// Negative values indicate errors (errno values).
+    pub fn result(&self) -> i32 {
+        self.result
+    }
+
+    /// Get the flags for this CQE
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Get the decoded flags for this CQE
+    pub fn cqe_flags(&self) -> CqeFlags {
+        CqeFlags::from_bits_truncate(self.flags)
+    }
+
+    /// Get the ID of the provided buffer this completion consumed
+    pub fn buffer_id(&self) -> Option<u16> {
+        buffer_id(self.flags)
+    }
+
+    /// Check if more completions will be posted for this request
+    pub fn has_more(&self) -> bool {
+        self.cqe_flags().contains(CqeFlags::MORE)
+    }
+
+    /// Check if the socket had more data queued after this receive
+    pub fn sock_nonempty(&self) -> bool {
+        self.cqe_flags().contains(CqeFlags::SOCK_NONEMPTY)
+    }
+
+    /// Check if this is a zero-copy send notification
+    pub fn is_notification(&self) -> bool {
+        self.cqe_flags().contains(CqeFlags::NOTIF)
+    }
+
+    /// Get the extra 16 bytes of a 32-byte CQE
+    ///
+    /// Returns `None` unless the ring was set up with `CQE32`.
+    pub fn big_cqe(&self) -> Option<&[u64; 2]> {
+        self.big_cqe.as_ref()
+    }
+
+    /// Check if the operation was successful
+    pub fn is_success(&self) -> bool {
+        self.result >= 0
+    }
+
+    /// Convert the result to a `std::io::Result`
+    pub fn into_result(self) -> std::io::Result<i32> {
+        if self.result < 0 {
+            Err(crate::error::from_ret_code(self.result))
+        } else {
+            Ok(self.result)
+        }
+    }
+}
+
+/// Decode the provided buffer ID from raw CQE flags
+pub(crate) fn buffer_id(flags: u32) -> Option<u16> {
+    if flags & sys::IORING_CQE_F_BUFFER != 0 {
+        Some((flags >> sys::IORING_CQE_BUFFER_SHIFT) as u16)
+    } else {
+        None
+    }
+}
+
 /// A single completion queue entry
 ///
 /// When dropped, the CQE is automatically marked as seen.
@@ -155,6 +672,27 @@ pub struct Cqe<'ring> {
 }
 
 impl<'ring> Cqe<'ring> {
+    /// Wrap the result of one of liburing's timed wait calls
+    pub(crate) fn from_wait(
+        ret: i32,
+        cqe: *mut sys::io_uring_cqe,
+        ring: *mut sys::io_uring,
+    ) -> Result<Option<Self>> {
+        if ret == -libc::ETIME {
+            Ok(None)
+        } else if ret < 0 {
+            Err(crate::error::from_ret_code(ret).into())
+        } else if cqe.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(Cqe {
+                cqe,
+                ring,
+                _phantom: PhantomData,
+            }))
+        }
+    }
+
     /// Get the user data that was set on the SQE
     pub fn user_data(&self) -> u64 {
         unsafe { (*self.cqe).user_data }
@@ -172,6 +710,61 @@ impl<'ring> Cqe<'ring> {
         unsafe { (*self.cqe).flags }
     }
 
+    /// Get the decoded flags for this CQE
+    ///
+    /// The buffer ID stored in the upper bits is not included; use
+    /// [`Cqe::buffer_id`] for that.
+    pub fn cqe_flags(&self) -> CqeFlags {
+        CqeFlags::from_bits_truncate(self.flags())
+    }
+
+    /// Get the ID of the provided buffer this completion consumed
+    ///
+    /// Returns `None` unless `IORING_CQE_F_BUFFER` is set.
+    pub fn buffer_id(&self) -> Option<u16> {
+        buffer_id(self.flags())
+    }
+
+    /// Check if more completions will be posted for this request
+    ///
+    /// Set on multishot completions that leave the request armed.
+    pub fn has_more(&self) -> bool {
+        self.cqe_flags().contains(CqeFlags::MORE)
+    }
+
+    /// Check if the socket had more data queued after this receive
+    pub fn sock_nonempty(&self) -> bool {
+        self.cqe_flags().contains(CqeFlags::SOCK_NONEMPTY)
+    }
+
+    /// Check if this is a zero-copy send notification
+    ///
+    /// Notifications signal that the kernel released the send buffer.
+    pub fn is_notification(&self) -> bool {
+        self.cqe_flags().contains(CqeFlags::NOTIF)
+    }
+
+    /// Get the extra 16 bytes of a 32-byte CQE
+    ///
+    /// Returns `None` unless the ring was set up with `CQE32`.
+    pub fn big_cqe(&self) -> Option<&[u64; 2]> {
+        let is_big = unsafe { (*self.ring).flags } & sys::IORING_SETUP_CQE32 != 0;
+        if !is_big {
+            return None;
+        }
+
+        unsafe {
+            let extra = (self.cqe as *const u8).add(std::mem::size_of::<sys::io_uring_cqe>());
+            Some(&*(extra as *const [u64; 2]))
+        }
+    }
+
+    /// Copy this entry out of the ring
+    pub fn to_completion(&self) -> Completion {
+        let big = unsafe { (*self.ring).flags } & sys::IORING_SETUP_CQE32 != 0;
+        unsafe { Completion::from_raw(self.cqe, big) }
+    }
+
     /// Check if the operation was successful
     pub fn is_success(&self) -> bool {
         self.result() >= 0
@@ -199,9 +792,22 @@ impl Drop for Cqe<'_> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_queue_operations() {
         // Basic smoke test - actual operations require a working io_uring
         // which may not be available in all test environments
     }
+
+    #[test]
+    fn test_buffer_id_decoding() {
+        assert_eq!(buffer_id(0), None);
+        assert_eq!(buffer_id(sys::IORING_CQE_F_MORE | (7 << 16)), None);
+        assert_eq!(buffer_id(sys::IORING_CQE_F_BUFFER | (7 << 16)), Some(7));
+        assert_eq!(
+            buffer_id(sys::IORING_CQE_F_BUFFER | sys::IORING_CQE_F_MORE | (0xffff << 16)),
+            Some(0xffff)
+        );
+    }
 }
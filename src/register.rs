@@ -0,0 +1,130 @@
+//! Ring registration operations
+
+use crate::error::{check_ret, Result};
+use crate::sys;
+use crate::types::{CpuSet, EventFd, WorkerLimits};
+use crate::uring::IoUring;
+use std::os::unix::io::AsRawFd;
+
+impl IoUring {
+    /// Register the ring fd with the ring itself
+    ///
+    /// Afterwards every `io_uring_enter` made through this ring passes the
+    /// registered index instead of the fd, which skips the fd lookup in the
+    /// kernel. Needs Linux 5.18.
+    ///
+    /// The registration belongs to the calling thread: submitting or waiting
+    /// from another thread fails with `EBADF` until
+    /// [`unregister_ring_fd`](Self::unregister_ring_fd) is called.
+    pub fn register_ring_fd(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_register_ring_fd(self.as_raw_mut()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Go back to entering the kernel through the plain ring fd
+    pub fn unregister_ring_fd(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_unregister_ring_fd(self.as_raw_mut()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Close the ring fd, leaving only the registered index
+    ///
+    /// Requires a prior [`register_ring_fd`](Self::register_ring_fd) and
+    /// `IORING_FEAT_REG_REG_RING`. Afterwards [`as_raw_fd`](Self::as_raw_fd)
+    /// returns -1, so the ring can no longer be polled for readiness or
+    /// shared with other threads.
+    pub fn close_ring_fd(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_close_ring_fd(self.as_raw_mut()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Signal `eventfd` whenever a completion is posted
+    ///
+    /// The kernel holds its own reference, so `eventfd` may be dropped
+    /// while registered. Only one eventfd can be registered at a time.
+    pub fn register_eventfd(&mut self, eventfd: &EventFd) -> Result<()> {
+        let ret = unsafe { sys::io_uring_register_eventfd(self.as_raw_mut(), eventfd.as_raw_fd()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Like [`register_eventfd`](Self::register_eventfd), but only signal
+    /// for requests that completed asynchronously, not inline at submit
+    pub fn register_eventfd_async(&mut self, eventfd: &EventFd) -> Result<()> {
+        let ret =
+            unsafe { sys::io_uring_register_eventfd_async(self.as_raw_mut(), eventfd.as_raw_fd()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Stop signalling the registered eventfd
+    pub fn unregister_eventfd(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_unregister_eventfd(self.as_raw_mut()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Pause or resume eventfd notifications (`IORING_CQ_EVENTFD_DISABLED`)
+    ///
+    /// Cheaper than unregistering, as it only flips a flag in the shared
+    /// ring. Needs Linux 5.8.
+    pub fn set_eventfd_enabled(&mut self, enabled: bool) -> Result<()> {
+        let ret = unsafe { sys::io_uring_cq_eventfd_toggle(self.as_raw_mut(), enabled) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Check if eventfd notifications are enabled
+    pub fn eventfd_enabled(&self) -> bool {
+        unsafe { sys::io_uring_cq_eventfd_enabled(self.as_raw()) }
+    }
+
+    /// Restrict the ring's io-wq workers to a set of CPUs
+    pub fn register_iowq_aff(&mut self, cpus: &CpuSet) -> Result<()> {
+        let ret = unsafe {
+            sys::io_uring_register_iowq_aff(self.as_raw_mut(), cpus.size(), cpus.as_ptr())
+        };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Let io-wq workers run on any CPU again
+    pub fn unregister_iowq_aff(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_unregister_iowq_aff(self.as_raw_mut()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Cap the number of io-wq worker threads
+    ///
+    /// Returns the limits that were in effect before. Fields set to 0 are
+    /// left unchanged, so passing `WorkerLimits::default()` only queries the
+    /// current limits. Needs Linux 5.15.
+    pub fn register_iowq_max_workers(&mut self, limits: WorkerLimits) -> Result<WorkerLimits> {
+        let mut values = [limits.bounded, limits.unbounded];
+        let ret = unsafe {
+            sys::io_uring_register_iowq_max_workers(self.as_raw_mut(), values.as_mut_ptr())
+        };
+        check_ret(ret)?;
+
+        Ok(WorkerLimits {
+            bounded: values[0],
+            unbounded: values[1],
+        })
+    }
+
+    /// Register the calling thread's credentials as a personality
+    ///
+    /// Returns an id that can be set on SQEs with
+    /// [`SqeExt::set_personality`](crate::ops::SqeExt::set_personality) to
+    /// issue them with these credentials, whichever thread submits them.
+    /// Switch credentials (e.g. with `setresuid`) before registering to
+    /// capture another user's identity. Needs Linux 5.6.
+    pub fn register_personality(&mut self) -> Result<u16> {
+        let ret = unsafe { sys::io_uring_register_personality(self.as_raw_mut()) };
+        Ok(check_ret(ret)? as u16)
+    }
+
+    /// Drop a personality registered with
+    /// [`register_personality`](Self::register_personality)
+    ///
+    /// SQEs already submitted with `id` keep their credentials.
+    pub fn unregister_personality(&mut self, id: u16) -> Result<()> {
+        let ret = unsafe { sys::io_uring_unregister_personality(self.as_raw_mut(), id.into()) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+}
@@ -0,0 +1,169 @@
+//! Restricting what a ring may be used for
+
+use crate::error::{check_ret, Error, Result};
+use crate::flags::{SetupFlags, SqeFlags};
+use crate::sys::{self, io_uring_op, io_uring_register_op};
+use crate::uring::IoUring;
+
+/// The operations a ring is allowed to perform
+///
+/// Restrictions are registered on a ring created with
+/// [`IoUringBuilder::disabled`](crate::IoUringBuilder::disabled), before
+/// [`IoUring::enable_rings`] is called. Once the ring is enabled the kernel
+/// fails any register call that was not allowed with `EACCES`. SQEs with an
+/// opcode that was not allowed, or flags outside the allowed and required
+/// sets, complete with `-EACCES` and end the current submit batch.
+/// Restrictions can't be changed or lifted afterwards.
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::{flags::SqeFlags, opcode::io_uring_op, IoUring, Restrictions};
+///
+/// let mut ring = IoUring::builder().disabled().build(32)?;
+///
+/// let restrictions = Restrictions::new()
+///     .allow_op(io_uring_op::IORING_OP_READ)
+///     .allow_op(io_uring_op::IORING_OP_WRITE)
+///     .allow_sqe_flags(SqeFlags::IO_LINK);
+/// ring.register_restrictions(&restrictions)?;
+/// ring.enable_rings()?;
+///
+/// // Hand `ring` to code that may only read and write
+/// # Ok::<(), liburing_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Restrictions {
+    register_ops: Vec<io_uring_register_op>,
+    ops: Vec<io_uring_op>,
+    allowed_flags: SqeFlags,
+    required_flags: SqeFlags,
+}
+
+impl Restrictions {
+    /// Create a set of restrictions that allows nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow an `io_uring_register` opcode
+    pub fn allow_register_op(mut self, op: io_uring_register_op) -> Self {
+        if !self.register_ops.contains(&op) {
+            self.register_ops.push(op);
+        }
+        self
+    }
+
+    /// Allow an SQE opcode
+    pub fn allow_op(mut self, op: io_uring_op) -> Self {
+        if !self.ops.contains(&op) {
+            self.ops.push(op);
+        }
+        self
+    }
+
+    /// Allow SQEs to carry these flags
+    pub fn allow_sqe_flags(mut self, flags: SqeFlags) -> Self {
+        self.allowed_flags |= flags;
+        self
+    }
+
+    /// Require every SQE to carry these flags
+    ///
+    /// Required flags are implicitly allowed.
+    pub fn require_sqe_flags(mut self, flags: SqeFlags) -> Self {
+        self.required_flags |= flags;
+        self
+    }
+
+    fn to_raw(&self) -> Vec<sys::io_uring_restriction> {
+        let entry = |opcode: sys::io_uring_register_restriction_op, value: u8| {
+            let mut res = sys::io_uring_restriction {
+                opcode: opcode as u16,
+                ..Default::default()
+            };
+            // All three union members are a single u8
+            res.__bindgen_anon_1.register_op = value;
+            res
+        };
+
+        let mut raw = Vec::with_capacity(self.register_ops.len() + self.ops.len() + 2);
+        raw.extend(self.register_ops.iter().map(|&op| {
+            entry(
+                sys::io_uring_register_restriction_op_IORING_RESTRICTION_REGISTER_OP,
+                op as u8,
+            )
+        }));
+        raw.extend(self.ops.iter().map(|&op| {
+            entry(
+                sys::io_uring_register_restriction_op_IORING_RESTRICTION_SQE_OP,
+                op as u8,
+            )
+        }));
+        // The kernel keeps only the last entry of each flags kind
+        if !self.allowed_flags.is_empty() {
+            raw.push(entry(
+                sys::io_uring_register_restriction_op_IORING_RESTRICTION_SQE_FLAGS_ALLOWED,
+                self.allowed_flags.bits(),
+            ));
+        }
+        if !self.required_flags.is_empty() {
+            raw.push(entry(
+                sys::io_uring_register_restriction_op_IORING_RESTRICTION_SQE_FLAGS_REQUIRED,
+                self.required_flags.bits(),
+            ));
+        }
+        raw
+    }
+}
+
+impl IoUring {
+    /// Limit the ring to the operations in `restrictions`
+    ///
+    /// The ring must have been created disabled and not enabled yet, and
+    /// restrictions can only be registered once. Needs Linux 5.10.
+    pub fn register_restrictions(&mut self, restrictions: &Restrictions) -> Result<()> {
+        if !self.setup_flags().contains(SetupFlags::R_DISABLED) {
+            return Err(Error::InvalidOperation(
+                "restrictions need a ring created with IORING_SETUP_R_DISABLED".to_string(),
+            ));
+        }
+
+        let mut raw = restrictions.to_raw();
+        let ret = unsafe {
+            sys::io_uring_register_restrictions(
+                self.as_raw_mut(),
+                raw.as_mut_ptr(),
+                raw.len() as u32,
+            )
+        };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restrictions_to_raw() {
+        let raw = Restrictions::new()
+            .allow_register_op(io_uring_register_op::IORING_REGISTER_BUFFERS)
+            .allow_op(io_uring_op::IORING_OP_NOP)
+            .allow_op(io_uring_op::IORING_OP_NOP)
+            .allow_sqe_flags(SqeFlags::IO_LINK)
+            .allow_sqe_flags(SqeFlags::IO_DRAIN)
+            .to_raw();
+
+        assert_eq!(raw.len(), 3);
+        assert_eq!(
+            raw[0].opcode as u32,
+            sys::io_uring_register_restriction_op_IORING_RESTRICTION_REGISTER_OP
+        );
+        assert_eq!(unsafe { raw[1].__bindgen_anon_1.sqe_op }, 0);
+        assert_eq!(
+            unsafe { raw[2].__bindgen_anon_1.sqe_flags },
+            (SqeFlags::IO_LINK | SqeFlags::IO_DRAIN).bits()
+        );
+    }
+}
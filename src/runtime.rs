@@ -0,0 +1,381 @@
+//! Thread-per-core executor parked on io_uring
+//!
+//! A [`Runtime`] is a single-threaded executor that owns one ring. When no
+//! task can make progress it parks in `io_uring_enter`, submitting the SQEs
+//! its tasks queued and sleeping until a CQE arrives; there is no epoll
+//! round-trip and no reactor thread. Timers are `IORING_OP_TIMEOUT` ops on
+//! the same ring, see [`time`].
+//!
+//! The ring is a [`local_impl`](crate::async_io::local_impl) one, so no
+//! lock guards it and the kernel runs completions only while the runtime
+//! waits. This needs Linux 6.1 or later.
+//!
+//! Tasks are `!Send` and stay on the thread that spawned them. To use every
+//! core, run one runtime per core with [`per_core`], each on a thread
+//! pinned to its CPU. Enable with the `runtime` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use liburing_rs::async_io::fs::File;
+//! use liburing_rs::runtime::{self, Runtime};
+//! use std::time::Duration;
+//!
+//! # fn main() -> liburing_rs::Result<()> {
+//! let rt = Runtime::new()?;
+//! rt.block_on(async {
+//!     let task = runtime::spawn_local(async {
+//!         runtime::sleep(Duration::from_millis(10)).await;
+//!         42
+//!     });
+//!     let file = File::open(&runtime::ring(), "/etc/hostname").await?;
+//!     println!("{} bytes", file.metadata().await?.len());
+//!     assert_eq!(task.await?, 42);
+//!     Ok(())
+//! })
+//! # }
+//! ```
+
+mod task;
+
+pub use task::{JoinHandle, LocalSet, RunUntil};
+
+use crate::async_io::{local_impl::AsyncIoUring, time};
+use crate::ops::PrepareOp;
+use crate::{sys, types::CpuSet, types::EventFd, Error, Result};
+use futures::task::{waker, ArcWake};
+use std::cell::{RefCell, UnsafeCell};
+use std::future::Future;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::pin::pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// Submission queue size of runtimes built with default settings
+const DEFAULT_ENTRIES: u32 = 256;
+
+thread_local! {
+    // Ring of the runtime whose `block_on` is running on this thread
+    static RING: RefCell<Option<AsyncIoUring>> = const { RefCell::new(None) };
+}
+
+/// Builder for a [`Runtime`]
+#[derive(Debug, Clone)]
+pub struct Builder {
+    entries: u32,
+    cpu: Option<usize>,
+}
+
+impl Builder {
+    /// Create a builder with default settings
+    pub fn new() -> Self {
+        Self {
+            entries: DEFAULT_ENTRIES,
+            cpu: None,
+        }
+    }
+
+    /// Number of submission queue entries of the ring
+    pub fn entries(mut self, entries: u32) -> Self {
+        self.entries = entries;
+        self
+    }
+
+    /// Pin the thread building the runtime to `cpu`
+    pub fn pin_to_cpu(mut self, cpu: usize) -> Self {
+        self.cpu = Some(cpu);
+        self
+    }
+
+    /// Set up the ring and pin the calling thread, if asked to
+    pub fn build(self) -> Result<Runtime> {
+        if let Some(cpu) = self.cpu {
+            let mut cpus = CpuSet::new();
+            cpus.add(cpu)?;
+            cpus.pin_current_thread()?;
+        }
+
+        let ring = AsyncIoUring::new(self.entries)?;
+        let unpark = Arc::new(Unpark {
+            woken: AtomicBool::new(false),
+            parked: AtomicBool::new(false),
+            thread: thread::current().id(),
+            eventfd: blocking_eventfd()?,
+        });
+
+        // Keeps a read of the eventfd in flight, so that a wake from
+        // another thread ends the wait for CQEs
+        let local = LocalSet::new();
+        let wakeups = ring.clone();
+        let efd = unpark.eventfd.as_raw_fd();
+        let wakeup_error = Rc::new(RefCell::new(None));
+        let failed = wakeup_error.clone();
+        drop(local.spawn_local(async move {
+            let err = loop {
+                match wakeups.submit_op(ReadCounter::new(efd)).await {
+                    Ok(res) if res >= 0 => {}
+                    Ok(res) => break Error::Io(io::Error::from_raw_os_error(-res)),
+                    Err(e) => break e,
+                }
+            };
+            *failed.borrow_mut() = Some(err);
+        }));
+
+        Ok(Runtime {
+            ring,
+            local,
+            unpark,
+            wakeup_error,
+        })
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Single-threaded executor that parks on its io_uring
+///
+/// The runtime and its tasks stay on the thread that created it; it is
+/// neither `Send` nor `Sync`.
+pub struct Runtime {
+    ring: AsyncIoUring,
+    // Tasks spawned with `spawn_local` outside any other `LocalSet`
+    local: LocalSet,
+    unpark: Arc<Unpark>,
+    // Why the eventfd read stopped, after which remote wakes are lost
+    wakeup_error: Rc<RefCell<Option<Error>>>,
+}
+
+/// Wakes the runtime's `block_on`, interrupting its wait if it is parked
+struct Unpark {
+    woken: AtomicBool,
+    parked: AtomicBool,
+    // The runtime's thread, whose wakes come from the ring's completions
+    thread: ThreadId,
+    eventfd: EventFd,
+}
+
+impl ArcWake for Unpark {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.woken.store(true, Ordering::SeqCst);
+        // Only another thread can wake a parked runtime without a CQE; a
+        // wake from the driver's dispatch is seen once `drive` returns
+        if arc_self.parked.load(Ordering::SeqCst) && thread::current().id() != arc_self.thread {
+            let _ = arc_self.eventfd.write(1);
+        }
+    }
+}
+
+impl Runtime {
+    /// Create a runtime with default settings
+    pub fn new() -> Result<Runtime> {
+        Builder::new().build()
+    }
+
+    /// Create a builder to configure a runtime
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// The runtime's ring, for opening files and sockets or submitting ops
+    pub fn ring(&self) -> &AsyncIoUring {
+        &self.ring
+    }
+
+    /// Spawn a `!Send` task, to run while `block_on` is running
+    pub fn spawn_local<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+    {
+        self.local.spawn_local(future)
+    }
+
+    /// Run `future` and the runtime's tasks until `future` completes
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring fails, since none of the tasks could make
+    /// progress after that, and if wakes from other threads can no longer
+    /// reach it. Also panics if called from within `block_on`.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let _enter = self.enter();
+        let mut future = pin!(self.local.run_until(future));
+        let waker = waker(self.unpark.clone());
+        let mut cx = Context::from_waker(&waker);
+        // The future hasn't been polled yet
+        self.unpark.woken.store(true, Ordering::SeqCst);
+
+        loop {
+            if self.unpark.woken.swap(false, Ordering::SeqCst) {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                continue;
+            }
+
+            // Park: submit what the tasks queued and sleep until a CQE
+            // arrives, unless a wake slipped in meanwhile
+            self.unpark.parked.store(true, Ordering::SeqCst);
+            let res = if self.unpark.woken.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                self.ring.drive()
+            };
+            self.unpark.parked.store(false, Ordering::SeqCst);
+            if let Err(e) = res {
+                panic!("io_uring runtime failed: {}", e);
+            }
+            if let Some(e) = &*self.wakeup_error.borrow() {
+                panic!("io_uring runtime can't be woken: {}", e);
+            }
+        }
+    }
+
+    fn enter(&self) -> EnterGuard {
+        RING.with(|ring| {
+            let mut ring = ring.borrow_mut();
+            assert!(ring.is_none(), "cannot block_on from within block_on");
+            *ring = Some(self.ring.clone());
+        });
+        EnterGuard
+    }
+}
+
+struct EnterGuard;
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        RING.with(|ring| ring.borrow_mut().take());
+    }
+}
+
+/// Spawn a `!Send` task on the current [`LocalSet`] or runtime
+///
+/// # Panics
+///
+/// Panics if called outside of [`Runtime::block_on`] and
+/// [`LocalSet::run_until`].
+pub fn spawn_local<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+{
+    LocalSet::current()
+        .expect("spawn_local called outside of a runtime")
+        .spawn_local(future)
+}
+
+/// The ring of the runtime running on this thread
+///
+/// # Panics
+///
+/// Panics if called outside of [`Runtime::block_on`].
+pub fn ring() -> AsyncIoUring {
+    try_ring().expect("no io_uring runtime running on this thread")
+}
+
+fn try_ring() -> Option<AsyncIoUring> {
+    RING.with(|ring| ring.borrow().clone())
+}
+
+/// Wait until `duration` has passed
+///
+/// Shorthand for [`time::sleep`] on the current runtime's ring.
+///
+/// # Panics
+///
+/// Panics if called outside of [`Runtime::block_on`].
+pub async fn sleep(duration: Duration) {
+    let ring = ring();
+    if let Err(e) = time::sleep(&ring, duration).await {
+        panic!("io_uring timeout failed: {}", e);
+    }
+}
+
+/// Wait until `deadline`
+///
+/// Shorthand for [`time::sleep_until`] on the current runtime's ring.
+///
+/// # Panics
+///
+/// Panics if called outside of [`Runtime::block_on`].
+pub async fn sleep_until(deadline: Instant) {
+    let ring = ring();
+    if let Err(e) = time::sleep_until(&ring, deadline).await {
+        panic!("io_uring timeout failed: {}", e);
+    }
+}
+
+/// Run one runtime per CPU the process may use, each on its own thread
+///
+/// Every thread is pinned to its CPU, builds a runtime with `builder`'s
+/// settings, and runs the future `f` returns for its CPU index. Returns
+/// the threads' handles, in CPU order.
+pub fn per_core<F, Fut>(
+    builder: Builder,
+    f: F,
+) -> Result<Vec<std::thread::JoinHandle<Result<Fut::Output>>>>
+where
+    F: Fn(usize) -> Fut + Send + Sync + 'static,
+    Fut: Future + 'static,
+    Fut::Output: Send + 'static,
+{
+    let f = Arc::new(f);
+    CpuSet::current()?
+        .iter()
+        .map(|cpu| {
+            let builder = builder.clone().pin_to_cpu(cpu);
+            let f = f.clone();
+            std::thread::Builder::new()
+                .name(format!("io_uring-{}", cpu))
+                .spawn(move || {
+                    let rt = builder.build()?;
+                    Ok(rt.block_on(f(cpu)))
+                })
+                .map_err(Error::Io)
+        })
+        .collect()
+}
+
+/// An eventfd whose reads wait for the counter to be set, instead of
+/// failing with `EAGAIN` like those of [`EventFd::new`]
+fn blocking_eventfd() -> Result<EventFd> {
+    let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(EventFd::from(unsafe { OwnedFd::from_raw_fd(fd) }))
+}
+
+/// Read of an eventfd's counter into a buffer owned by the op
+struct ReadCounter {
+    fd: RawFd,
+    value: UnsafeCell<u64>,
+}
+
+// SAFETY: `value` is only written by the kernel while the op is in flight
+unsafe impl Send for ReadCounter {}
+
+impl ReadCounter {
+    fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            value: UnsafeCell::new(0),
+        }
+    }
+}
+
+impl PrepareOp for ReadCounter {
+    fn prepare(&self, sqe: &mut sys::io_uring_sqe) {
+        unsafe {
+            sys::io_uring_prep_read(sqe, self.fd, self.value.get().cast(), 8, 0);
+        }
+    }
+}
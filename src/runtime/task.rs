@@ -0,0 +1,288 @@
+//! Local tasks and the sets that run them
+
+use crate::{Error, Result};
+use futures::task::{waker, ArcWake, AtomicWaker};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+thread_local! {
+    // The set whose `run_until` is being polled on this thread
+    static CURRENT: RefCell<Option<LocalSet>> = const { RefCell::new(None) };
+}
+
+/// A set of `!Send` tasks run on the thread that polls it
+///
+/// Tasks spawned on the set only make progress while
+/// [`run_until`](Self::run_until) is polled, from the runtime or from any
+/// other executor. While it is, [`spawn_local`](super::spawn_local) spawns
+/// onto this set. Dropping the set drops its unfinished tasks, which
+/// cancels the io_uring ops they are waiting for.
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::runtime::{LocalSet, Runtime};
+/// use std::rc::Rc;
+///
+/// # fn main() -> liburing_rs::Result<()> {
+/// let rt = Runtime::new()?;
+/// let local = LocalSet::new();
+/// let shared = Rc::new(5);
+///
+/// let task = local.spawn_local({
+///     let shared = shared.clone();
+///     async move { *shared * 2 }
+/// });
+/// let doubled = rt.block_on(local.run_until(task))?;
+/// assert_eq!(doubled, 10);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct LocalSet {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    shared: Arc<Shared>,
+    tasks: RefCell<HashMap<usize, Task>>,
+    next_id: Cell<usize>,
+}
+
+/// The part of a set that wakers reach, possibly from other threads
+#[derive(Default)]
+struct Shared {
+    ready: Mutex<VecDeque<usize>>,
+    // The task polling `run_until`
+    waker: AtomicWaker,
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    waker: Arc<TaskWaker>,
+}
+
+struct TaskWaker {
+    id: usize,
+    shared: Arc<Shared>,
+    // Set while the task sits in the ready queue
+    queued: AtomicBool,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if !arc_self.queued.swap(true, Ordering::AcqRel) {
+            arc_self.shared.ready.lock().unwrap().push_back(arc_self.id);
+            arc_self.shared.waker.wake();
+        }
+    }
+}
+
+impl LocalSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a `!Send` task on the set
+    ///
+    /// The task starts on the next poll of `run_until`. Dropping the
+    /// returned handle detaches the task.
+    pub fn spawn_local<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+    {
+        let state = Rc::new(RefCell::new(JoinState::default()));
+        let task = {
+            let guard = Completion(state.clone());
+            async move {
+                let output = future.await;
+                guard.complete(output);
+            }
+        };
+
+        let id = self.inner.next_id.get();
+        self.inner.next_id.set(id.wrapping_add(1));
+        let waker = Arc::new(TaskWaker {
+            id,
+            shared: self.inner.shared.clone(),
+            queued: AtomicBool::new(false),
+        });
+        ArcWake::wake_by_ref(&waker);
+        self.inner.tasks.borrow_mut().insert(
+            id,
+            Task {
+                future: Box::pin(task),
+                waker,
+            },
+        );
+        JoinHandle { state }
+    }
+
+    /// Run the set's tasks until `future` completes
+    ///
+    /// Tasks still running when it does stay in the set, and carry on the
+    /// next time the set is run.
+    pub fn run_until<F: Future>(&self, future: F) -> RunUntil<'_, F> {
+        RunUntil { set: self, future }
+    }
+
+    /// Number of tasks that haven't finished
+    pub fn len(&self) -> usize {
+        self.inner.tasks.borrow().len()
+    }
+
+    /// Check if every task has finished
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The set `run_until` is running on this thread, if any
+    pub(super) fn current() -> Option<LocalSet> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Poll the tasks that were ready when called
+    ///
+    /// Tasks woken meanwhile wait for the next call, so one busy task can't
+    /// starve the future passed to `run_until`. Returns whether any task is
+    /// ready again.
+    fn poll_ready(&self) -> bool {
+        let ready: Vec<usize> = self.inner.shared.ready.lock().unwrap().drain(..).collect();
+        for id in ready {
+            // Taken out so the task may spawn more
+            let Some(mut task) = self.inner.tasks.borrow_mut().remove(&id) else {
+                continue;
+            };
+            task.waker.queued.store(false, Ordering::Release);
+
+            let waker = waker(task.waker.clone());
+            let mut cx = Context::from_waker(&waker);
+            if task.future.as_mut().poll(&mut cx).is_pending() {
+                self.inner.tasks.borrow_mut().insert(id, task);
+            }
+        }
+        !self.inner.shared.ready.lock().unwrap().is_empty()
+    }
+
+    /// Make this the current set until the guard is dropped
+    fn enter(&self) -> EnterGuard {
+        let prev = CURRENT.with(|current| current.replace(Some(self.clone())));
+        EnterGuard { prev }
+    }
+}
+
+struct EnterGuard {
+    prev: Option<LocalSet>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+/// Future returned by [`LocalSet::run_until`]
+pub struct RunUntil<'a, F> {
+    set: &'a LocalSet,
+    future: F,
+}
+
+impl<F: Future> Future for RunUntil<'_, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // SAFETY: `future` is never moved out of the pinned struct
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let set = this.set;
+        let _enter = set.enter();
+        set.inner.shared.waker.register(cx.waker());
+
+        if let Poll::Ready(output) = future.poll(cx) {
+            return Poll::Ready(output);
+        }
+        if set.poll_ready() {
+            // Come back for the tasks woken in the meantime
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+/// Handle to a task spawned with `spawn_local`, resolving with its output
+///
+/// Resolves with an error if the task is dropped before it finishes,
+/// which happens when its [`LocalSet`] is dropped.
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    // Set once the task finished or was dropped
+    done: bool,
+    waker: Option<Waker>,
+}
+
+impl<T> Default for JoinState<T> {
+    fn default() -> Self {
+        Self {
+            output: None,
+            done: false,
+            waker: None,
+        }
+    }
+}
+
+/// Hands a task's output to its `JoinHandle`, or reports it dropped
+struct Completion<T>(Rc<RefCell<JoinState<T>>>);
+
+impl<T> Completion<T> {
+    fn complete(self, output: T) {
+        self.0.borrow_mut().output = Some(output);
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.0.borrow_mut();
+            state.done = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> JoinHandle<T> {
+    /// Check if the task has finished
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().done
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        let mut state = self.state.borrow_mut();
+        if !state.done {
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(state.output.take().ok_or_else(|| {
+            Error::InvalidOperation("task was dropped before it finished".to_string())
+        }))
+    }
+}
@@ -0,0 +1,164 @@
+//! Submitter and completer halves of a ring
+//!
+//! The SQ and CQ are single-producer/single-consumer rings with disjoint
+//! state: the submitter owns the SQ tail, the completer owns the CQ head.
+//! liburing publishes both with release stores and reads the kernel's side
+//! with acquire loads, so each half can run on its own thread.
+//!
+//! A `SINGLE_ISSUER` ring only accepts submissions from the thread that
+//! created it. Its submission half is a [`LocalSubmitter`], which can't be
+//! sent to another thread; the completion half still can.
+
+use crate::error::{Error, Result};
+use crate::flags::SetupFlags;
+use crate::queue::{CompletionQueue, SubmissionQueue};
+use crate::uring::IoUring;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// The submission half of a split ring
+///
+/// Dereferences to [`SubmissionQueue`].
+pub struct Submitter<'a> {
+    sq: SubmissionQueue<'a>,
+}
+
+/// The submission half of a split ring that stays on its thread
+///
+/// Returned by [`IoUring::split_local`]. Dereferences to
+/// [`SubmissionQueue`], like [`Submitter`], but is not `Send`.
+pub struct LocalSubmitter<'a> {
+    sq: SubmissionQueue<'a>,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// The completion half of a split ring
+///
+/// Dereferences to [`CompletionQueue`]. Timed waits need
+/// [`Features::EXT_ARG`](crate::flags::Features::EXT_ARG) (Linux 5.11), as
+/// older kernels would require queueing a timeout SQE from this thread.
+pub struct Completer<'a> {
+    cq: CompletionQueue<'a>,
+}
+
+impl IoUring {
+    /// Split the ring into halves that can be used from different threads
+    ///
+    /// # Errors
+    ///
+    /// Rings set up with `SINGLE_ISSUER` must be driven from the thread
+    /// that created them; split them with
+    /// [`split_local`](Self::split_local) instead.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use liburing_rs::{ops::*, IoUring};
+    ///
+    /// let mut ring = IoUring::new(32)?;
+    /// let (mut submitter, mut completer) = ring.split()?;
+    ///
+    /// std::thread::scope(|s| {
+    ///     s.spawn(move || {
+    ///         let cqe = completer.wait_cqe().unwrap();
+    ///         println!("{}", cqe.result());
+    ///     });
+    ///
+    ///     let sqe = submitter.get_sqe_or_err().unwrap();
+    ///     Nop.prepare(sqe);
+    ///     submitter.submit().unwrap();
+    /// });
+    /// # Ok::<(), liburing_rs::Error>(())
+    /// ```
+    pub fn split(&mut self) -> Result<(Submitter<'_>, Completer<'_>)> {
+        if self.setup_flags().contains(SetupFlags::SINGLE_ISSUER) {
+            return Err(Error::InvalidOperation(
+                "a SINGLE_ISSUER ring can only be split with split_local".to_string(),
+            ));
+        }
+
+        let (sq, cq) = self.split_queues()?;
+        Ok((Submitter { sq }, Completer { cq }))
+    }
+
+    /// Split the ring, keeping the submission half on this thread
+    ///
+    /// Works for `SINGLE_ISSUER` rings too: the [`LocalSubmitter`] can't
+    /// leave the thread, while the [`Completer`] may reap completions on
+    /// any other.
+    ///
+    /// # Errors
+    ///
+    /// With `DEFER_TASKRUN` completions are only posted when the submitting
+    /// thread asks for them, so such rings cannot be split.
+    pub fn split_local(&mut self) -> Result<(LocalSubmitter<'_>, Completer<'_>)> {
+        let (sq, cq) = self.split_queues()?;
+        let submitter = LocalSubmitter {
+            sq,
+            _not_send: PhantomData,
+        };
+        Ok((submitter, Completer { cq }))
+    }
+
+    fn split_queues(&mut self) -> Result<(SubmissionQueue<'_>, CompletionQueue<'_>)> {
+        if self.setup_flags().contains(SetupFlags::DEFER_TASKRUN) {
+            return Err(Error::InvalidOperation(
+                "cannot split a DEFER_TASKRUN ring".to_string(),
+            ));
+        }
+
+        let (ring, policy, wakeups) = self.sq_parts();
+        Ok(unsafe {
+            (
+                SubmissionQueue::from_raw(ring, policy, wakeups),
+                CompletionQueue::from_raw_shared(ring),
+            )
+        })
+    }
+}
+
+impl<'a> Deref for Submitter<'a> {
+    type Target = SubmissionQueue<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sq
+    }
+}
+
+impl DerefMut for Submitter<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.sq
+    }
+}
+
+impl<'a> Deref for LocalSubmitter<'a> {
+    type Target = SubmissionQueue<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sq
+    }
+}
+
+impl DerefMut for LocalSubmitter<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.sq
+    }
+}
+
+impl<'a> Deref for Completer<'a> {
+    type Target = CompletionQueue<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cq
+    }
+}
+
+impl DerefMut for Completer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cq
+    }
+}
+
+// Each half only touches its own side of the shared ring state
+unsafe impl Send for Submitter<'_> {}
+unsafe impl Send for Completer<'_> {}
@@ -0,0 +1,256 @@
+//! Ring statistics and kernel-side introspection
+
+use crate::error::{Error, Result};
+use crate::flags::SqFlags;
+use crate::uring::IoUring;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A snapshot of the ring's shared state
+///
+/// Counters are read from the mapped ring without entering the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingStats {
+    /// SQEs queued but not yet consumed by the kernel
+    pub sq_ready: u32,
+    /// Number of SQ slots
+    pub sq_entries: u32,
+    /// CQEs waiting to be reaped
+    pub cq_ready: u32,
+    /// Number of CQ slots
+    pub cq_entries: u32,
+    /// SQEs the kernel dropped because they were invalid
+    pub sq_dropped: u32,
+    /// CQEs lost to CQ overflow
+    pub cq_overflow: u32,
+    /// Flags the kernel sets on the SQ ring
+    pub sq_flags: SqFlags,
+    /// Times submission had to wake an idle SQPOLL thread
+    pub sqpoll_wakeups: u64,
+}
+
+impl RingStats {
+    /// Check if completions are held back in the kernel's overflow list
+    ///
+    /// They are flushed once there is room in the CQ again.
+    pub fn has_cq_overflow(&self) -> bool {
+        self.sq_flags.contains(SqFlags::CQ_OVERFLOW)
+    }
+
+    /// Check if task work is pending and needs a `get_events` call
+    pub fn has_taskrun(&self) -> bool {
+        self.sq_flags.contains(SqFlags::TASKRUN)
+    }
+
+    /// Check if the SQPOLL thread is asleep and needs a wakeup
+    pub fn needs_wakeup(&self) -> bool {
+        self.sq_flags.contains(SqFlags::NEED_WAKEUP)
+    }
+}
+
+/// Load a counter the kernel updates concurrently
+unsafe fn load(ptr: *const u32, order: Ordering) -> u32 {
+    (*(ptr as *const AtomicU32)).load(order)
+}
+
+impl IoUring {
+    /// Take a snapshot of the ring's occupancy and error counters
+    pub fn stats(&self) -> RingStats {
+        let ring = self.as_raw();
+
+        unsafe {
+            let sq = &ring.sq;
+            let cq = &ring.cq;
+
+            let sq_head = load(sq.khead, Ordering::Acquire);
+            let cq_tail = load(cq.ktail, Ordering::Acquire);
+
+            RingStats {
+                sq_ready: sq.sqe_tail.wrapping_sub(sq_head),
+                sq_entries: sq.ring_entries,
+                cq_ready: cq_tail.wrapping_sub(*cq.khead),
+                cq_entries: cq.ring_entries,
+                sq_dropped: load(sq.kdropped, Ordering::Relaxed),
+                cq_overflow: load(cq.koverflow, Ordering::Relaxed),
+                sq_flags: SqFlags::from_bits_retain(load(sq.kflags, Ordering::Relaxed)),
+                sqpoll_wakeups: self.sqpoll_wakeups(),
+            }
+        }
+    }
+
+    /// Read the kernel's view of the ring from `/proc/self/fdinfo`
+    pub fn fdinfo(&self) -> Result<FdInfo> {
+        FdInfo::read(self.as_raw_fd())
+    }
+}
+
+/// Kernel-side ring details from `/proc/self/fdinfo/<ring_fd>`
+///
+/// The set of fields varies between kernel versions, so every accessor
+/// returns an `Option`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FdInfo {
+    fields: Vec<(String, String)>,
+}
+
+impl FdInfo {
+    /// Read and parse the fdinfo of an io_uring file descriptor
+    pub fn read(fd: RawFd) -> Result<Self> {
+        let text = std::fs::read_to_string(format!("/proc/self/fdinfo/{}", fd))?;
+        let info = Self::parse(&text);
+
+        if info.get("SqMask").is_none() {
+            return Err(Error::InvalidOperation(format!(
+                "fd {} is not an io_uring instance",
+                fd
+            )));
+        }
+
+        Ok(info)
+    }
+
+    /// Parse fdinfo text
+    ///
+    /// Indented lines (the entries of `PollList` and friends) are skipped.
+    pub fn parse(text: &str) -> Self {
+        let fields = text
+            .lines()
+            .filter(|line| !line.starts_with(char::is_whitespace))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        Self { fields }
+    }
+
+    /// Get the raw value of a field
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_num(&self, key: &str) -> Option<u64> {
+        let value = self.get(key)?;
+        match value.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        }
+    }
+
+    fn get_u32(&self, key: &str) -> Option<u32> {
+        self.get_num(key).map(|v| v as u32)
+    }
+
+    /// SQ ring mask
+    pub fn sq_mask(&self) -> Option<u32> {
+        self.get_u32("SqMask")
+    }
+
+    /// SQ head as seen by the kernel
+    pub fn sq_head(&self) -> Option<u32> {
+        self.get_u32("SqHead")
+    }
+
+    /// SQ tail as published by the application
+    pub fn sq_tail(&self) -> Option<u32> {
+        self.get_u32("SqTail")
+    }
+
+    /// SQ head the kernel has consumed up to internally
+    pub fn cached_sq_head(&self) -> Option<u32> {
+        self.get_u32("CachedSqHead")
+    }
+
+    /// CQ ring mask
+    pub fn cq_mask(&self) -> Option<u32> {
+        self.get_u32("CqMask")
+    }
+
+    /// CQ head as published by the application
+    pub fn cq_head(&self) -> Option<u32> {
+        self.get_u32("CqHead")
+    }
+
+    /// CQ tail as seen by the application
+    pub fn cq_tail(&self) -> Option<u32> {
+        self.get_u32("CqTail")
+    }
+
+    /// CQ tail including CQEs the kernel has not published yet
+    pub fn cached_cq_tail(&self) -> Option<u32> {
+        self.get_u32("CachedCqTail")
+    }
+
+    /// PID of the SQPOLL thread, if the ring has one
+    pub fn sq_thread(&self) -> Option<i32> {
+        self.get("SqThread")?.parse().ok().filter(|&pid| pid >= 0)
+    }
+
+    /// CPU the SQPOLL thread is running on, if the ring has one
+    pub fn sq_thread_cpu(&self) -> Option<i32> {
+        self.get("SqThreadCpu")?
+            .parse()
+            .ok()
+            .filter(|&cpu| cpu >= 0)
+    }
+
+    /// Number of registered files
+    pub fn user_files(&self) -> Option<u32> {
+        self.get_u32("UserFiles")
+    }
+
+    /// Number of registered buffers
+    pub fn user_bufs(&self) -> Option<u32> {
+        self.get_u32("UserBufs")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FDINFO: &str = "pos:\t0
+flags:\t02000002
+mnt_id:\t17
+ino:\t31724
+SqMask:\t0x7
+SqHead:\t3
+SqTail:\t5
+CachedSqHead:\t5
+CqMask:\t0xf
+CqHead:\t1
+CqTail:\t4
+CachedCqTail:\t4
+SQEs:\t0
+CQEs:\t3
+SqThread:\t-1
+SqThreadCpu:\t-1
+UserFiles:\t2
+UserBufs:\t0
+PollList:
+  op=6, task_works=0
+CqOverflowList:
+";
+
+    #[test]
+    fn test_parse_fdinfo() {
+        let info = FdInfo::parse(FDINFO);
+
+        assert_eq!(info.sq_mask(), Some(7));
+        assert_eq!(info.sq_head(), Some(3));
+        assert_eq!(info.sq_tail(), Some(5));
+        assert_eq!(info.cached_sq_head(), Some(5));
+        assert_eq!(info.cq_mask(), Some(15));
+        assert_eq!(info.cq_head(), Some(1));
+        assert_eq!(info.cq_tail(), Some(4));
+        assert_eq!(info.cached_cq_tail(), Some(4));
+        assert_eq!(info.sq_thread(), None);
+        assert_eq!(info.sq_thread_cpu(), None);
+        assert_eq!(info.user_files(), Some(2));
+        assert_eq!(info.get("PollList"), Some(""));
+        assert_eq!(info.get("op=6, task_works=0"), None);
+        assert_eq!(info.get("SqTotalTime"), None);
+    }
+}
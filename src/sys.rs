@@ -37,6 +37,12 @@ extern "C" {
     pub fn io_uring_cq_advance(ring: *mut io_uring, nr: ::std::os::raw::c_uint);
     pub fn io_uring_cqe_get_data(cqe: *const io_uring_cqe) -> *mut ::std::os::raw::c_void;
     pub fn io_uring_cqe_get_data64(cqe: *const io_uring_cqe) -> u64;
+    pub fn io_uring_cq_has_overflow(ring: *const io_uring) -> bool;
+
+    // Ring state
+    pub fn io_uring_sqring_wait(ring: *mut io_uring) -> ::std::os::raw::c_int;
+    pub fn io_uring_cq_eventfd_enabled(ring: *const io_uring) -> bool;
+    pub fn io_uring_cq_eventfd_toggle(ring: *mut io_uring, enabled: bool) -> ::std::os::raw::c_int;
 
     // Prep operations - Basic I/O
     pub fn io_uring_prep_rw(
@@ -106,6 +112,15 @@ extern "C" {
         flags: ::std::os::raw::c_int,
         mode: libc::mode_t,
     );
+    pub fn io_uring_prep_statx(
+        sqe: *mut io_uring_sqe,
+        dfd: ::std::os::raw::c_int,
+        path: *const ::std::os::raw::c_char,
+        flags: ::std::os::raw::c_int,
+        mask: ::std::os::raw::c_uint,
+        statxbuf: *mut libc::statx,
+    );
+    pub fn io_uring_prep_ftruncate(sqe: *mut io_uring_sqe, fd: ::std::os::raw::c_int, len: i64);
 
     // Prep operations - Network
     pub fn io_uring_prep_accept(
@@ -115,6 +130,13 @@ extern "C" {
         addrlen: *mut libc::socklen_t,
         flags: ::std::os::raw::c_int,
     );
+    pub fn io_uring_prep_multishot_accept(
+        sqe: *mut io_uring_sqe,
+        fd: ::std::os::raw::c_int,
+        addr: *mut libc::sockaddr,
+        addrlen: *mut libc::socklen_t,
+        flags: ::std::os::raw::c_int,
+    );
     pub fn io_uring_prep_connect(
         sqe: *mut io_uring_sqe,
         fd: ::std::os::raw::c_int,
@@ -156,11 +178,21 @@ extern "C" {
         count: ::std::os::raw::c_uint,
         flags: ::std::os::raw::c_uint,
     );
+    pub fn io_uring_prep_link_timeout(
+        sqe: *mut io_uring_sqe,
+        ts: *mut __kernel_timespec,
+        flags: ::std::os::raw::c_uint,
+    );
     pub fn io_uring_prep_poll_add(
         sqe: *mut io_uring_sqe,
         fd: ::std::os::raw::c_int,
         poll_mask: ::std::os::raw::c_uint,
     );
+    pub fn io_uring_prep_poll_multishot(
+        sqe: *mut io_uring_sqe,
+        fd: ::std::os::raw::c_int,
+        poll_mask: ::std::os::raw::c_uint,
+    );
     pub fn io_uring_prep_poll_remove(sqe: *mut io_uring_sqe, user_data: u64);
     pub fn io_uring_prep_cancel(
         sqe: *mut io_uring_sqe,
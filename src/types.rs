@@ -0,0 +1,417 @@
+//! Helper types passed to io_uring calls
+
+use crate::error::Result;
+use crate::sys;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+/// A set of signals, used to mask signals while waiting for completions
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::types::SigSet;
+///
+/// let mut mask = SigSet::empty();
+/// mask.add(libc::SIGINT)?;
+/// # Ok::<(), liburing_rs::Error>(())
+/// ```
+#[derive(Clone, Copy)]
+pub struct SigSet {
+    set: libc::sigset_t,
+}
+
+impl SigSet {
+    /// Create a set with no signals
+    pub fn empty() -> Self {
+        let mut set = MaybeUninit::<libc::sigset_t>::uninit();
+        unsafe {
+            libc::sigemptyset(set.as_mut_ptr());
+            Self {
+                set: set.assume_init(),
+            }
+        }
+    }
+
+    /// Create a set with every signal
+    pub fn full() -> Self {
+        let mut set = MaybeUninit::<libc::sigset_t>::uninit();
+        unsafe {
+            libc::sigfillset(set.as_mut_ptr());
+            Self {
+                set: set.assume_init(),
+            }
+        }
+    }
+
+    /// Add a signal to the set
+    pub fn add(&mut self, signal: i32) -> Result<()> {
+        let ret = unsafe { libc::sigaddset(&mut self.set, signal) };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Remove a signal from the set
+    pub fn remove(&mut self, signal: i32) -> Result<()> {
+        let ret = unsafe { libc::sigdelset(&mut self.set, signal) };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Check if a signal is in the set
+    pub fn contains(&self, signal: i32) -> bool {
+        unsafe { libc::sigismember(&self.set, signal) == 1 }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut sys::sigset_t {
+        // liburing only reads the mask
+        &self.set as *const libc::sigset_t as *mut libc::sigset_t as *mut sys::sigset_t
+    }
+}
+
+impl std::fmt::Debug for SigSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let signals: Vec<i32> = (1..libc::SIGRTMAX())
+            .filter(|&sig| self.contains(sig))
+            .collect();
+        f.debug_struct("SigSet").field("signals", &signals).finish()
+    }
+}
+
+/// An owned eventfd, used to get notified of new completions
+///
+/// The descriptor is non-blocking and close-on-exec.
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::{types::EventFd, IoUring};
+///
+/// let mut ring = IoUring::new(32)?;
+/// let efd = EventFd::new()?;
+/// ring.register_eventfd(&efd)?;
+/// // Add `efd` to an epoll set; it becomes readable when CQEs are posted
+/// # Ok::<(), liburing_rs::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct EventFd {
+    fd: OwnedFd,
+}
+
+impl EventFd {
+    /// Create a new eventfd with a zero counter
+    pub fn new() -> Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Read and reset the counter
+    ///
+    /// Fails with `WouldBlock` if the counter is zero.
+    pub fn read(&self) -> io::Result<u64> {
+        let mut value = 0u64;
+        let ret = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                &mut value as *mut u64 as *mut libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(value)
+    }
+
+    /// Add `value` to the counter
+    pub fn write(&self, value: u64) -> io::Result<()> {
+        let ret = unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl AsRawFd for EventFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl AsFd for EventFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl IntoRawFd for EventFd {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for EventFd {
+    fn from(fd: OwnedFd) -> Self {
+        Self { fd }
+    }
+}
+
+/// A set of CPUs, used to pin io-wq workers
+///
+/// # Example
+///
+/// ```no_run
+/// use liburing_rs::{types::CpuSet, IoUring};
+///
+/// let mut ring = IoUring::new(32)?;
+/// let mut cpus = CpuSet::new();
+/// cpus.add(0)?;
+/// cpus.add(1)?;
+/// ring.register_iowq_aff(&cpus)?;
+/// # Ok::<(), liburing_rs::Error>(())
+/// ```
+#[derive(Clone, Copy)]
+pub struct CpuSet {
+    set: libc::cpu_set_t,
+}
+
+impl CpuSet {
+    /// Largest CPU index the set can hold, plus one
+    pub const CAPACITY: usize = libc::CPU_SETSIZE as usize;
+
+    /// Create an empty set
+    pub fn new() -> Self {
+        let mut set = MaybeUninit::<libc::cpu_set_t>::zeroed();
+        unsafe {
+            libc::CPU_ZERO(set.assume_init_mut());
+            Self {
+                set: set.assume_init(),
+            }
+        }
+    }
+
+    /// Add a CPU to the set
+    pub fn add(&mut self, cpu: usize) -> Result<()> {
+        Self::check(cpu)?;
+        unsafe { libc::CPU_SET(cpu, &mut self.set) };
+        Ok(())
+    }
+
+    /// Remove a CPU from the set
+    pub fn remove(&mut self, cpu: usize) -> Result<()> {
+        Self::check(cpu)?;
+        unsafe { libc::CPU_CLR(cpu, &mut self.set) };
+        Ok(())
+    }
+
+    /// Check if a CPU is in the set
+    pub fn contains(&self, cpu: usize) -> bool {
+        cpu < Self::CAPACITY && unsafe { libc::CPU_ISSET(cpu, &self.set) }
+    }
+
+    /// Number of CPUs in the set
+    pub fn len(&self) -> usize {
+        unsafe { libc::CPU_COUNT(&self.set) as usize }
+    }
+
+    /// Check if the set has no CPUs
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The CPUs the calling thread is allowed to run on
+    pub fn current() -> Result<Self> {
+        let mut cpus = Self::new();
+        let ret = unsafe { libc::sched_getaffinity(0, cpus.size(), &mut cpus.set) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(cpus)
+    }
+
+    /// Restrict the calling thread to the CPUs in the set
+    pub fn pin_current_thread(&self) -> Result<()> {
+        let ret = unsafe { libc::sched_setaffinity(0, self.size(), &self.set) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// The CPUs in the set, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Self::CAPACITY).filter(|&cpu| self.contains(cpu))
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const sys::cpu_set_t {
+        (&self.set as *const libc::cpu_set_t).cast()
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        std::mem::size_of::<libc::cpu_set_t>()
+    }
+
+    fn check(cpu: usize) -> Result<()> {
+        if cpu >= Self::CAPACITY {
+            return Err(crate::Error::InvalidParameter {
+                field: "cpu",
+                reason: format!("CPU {} is out of range (max {})", cpu, Self::CAPACITY - 1),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for CpuSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for CpuSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries((0..Self::CAPACITY).filter(|&cpu| self.contains(cpu)))
+            .finish()
+    }
+}
+
+/// Limits on the number of io-wq worker threads, per NUMA node
+///
+/// Bounded workers handle requests that finish in bounded time, such as
+/// regular file I/O; unbounded workers handle ones that may block forever,
+/// such as socket reads. A limit of 0 leaves the current value unchanged.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerLimits {
+    /// Maximum number of bounded workers
+    pub bounded: u32,
+    /// Maximum number of unbounded workers
+    pub unbounded: u32,
+}
+
+/// Convert a duration to the kernel's timespec
+pub(crate) fn timespec(duration: Duration) -> sys::__kernel_timespec {
+    sys::__kernel_timespec {
+        tv_sec: duration.as_secs() as _,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
+
+/// Convert a point in time to the kernel's timespec on `CLOCK_MONOTONIC`,
+/// the clock `Instant` reads
+///
+/// A deadline that has passed comes out as the current time.
+pub(crate) fn monotonic_timespec(deadline: Instant) -> sys::__kernel_timespec {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+    let now = Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+    timespec(now + deadline.saturating_duration_since(Instant::now()))
+}
+
+/// Get the raw pointer for an optional signal mask
+pub(crate) fn sigmask_ptr(sigmask: Option<&SigSet>) -> *mut sys::sigset_t {
+    sigmask.map_or(std::ptr::null_mut(), SigSet::as_ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigset() {
+        let mut set = SigSet::empty();
+        assert!(!set.contains(libc::SIGINT));
+
+        set.add(libc::SIGINT).unwrap();
+        assert!(set.contains(libc::SIGINT));
+
+        set.remove(libc::SIGINT).unwrap();
+        assert!(!set.contains(libc::SIGINT));
+
+        assert!(SigSet::full().contains(libc::SIGTERM));
+        assert!(set.add(-1).is_err());
+    }
+
+    #[test]
+    fn test_eventfd_counter() {
+        let efd = EventFd::new().unwrap();
+        assert_eq!(
+            efd.read().unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+
+        efd.write(2).unwrap();
+        efd.write(3).unwrap();
+        assert_eq!(efd.read().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_cpuset() {
+        let mut cpus = CpuSet::new();
+        assert!(cpus.is_empty());
+
+        cpus.add(0).unwrap();
+        cpus.add(3).unwrap();
+        assert!(cpus.contains(3));
+        assert!(!cpus.contains(2));
+        assert_eq!(cpus.len(), 2);
+
+        cpus.remove(3).unwrap();
+        assert_eq!(cpus.len(), 1);
+
+        assert!(cpus.add(CpuSet::CAPACITY).is_err());
+        assert!(!cpus.contains(CpuSet::CAPACITY));
+    }
+
+    #[test]
+    fn test_cpuset_current() {
+        let cpus = CpuSet::current().unwrap();
+        assert!(!cpus.is_empty());
+        assert_eq!(cpus.iter().count(), cpus.len());
+
+        // Pinning to the CPUs already allowed changes nothing
+        cpus.pin_current_thread().unwrap();
+        assert_eq!(CpuSet::current().unwrap().len(), cpus.len());
+    }
+
+    #[test]
+    fn test_timespec() {
+        let ts = timespec(Duration::from_millis(1500));
+        assert_eq!(ts.tv_sec, 1);
+        assert_eq!(ts.tv_nsec, 500_000_000);
+    }
+
+    #[test]
+    fn test_monotonic_timespec() {
+        let nanos =
+            |ts: sys::__kernel_timespec| ts.tv_sec as i128 * 1_000_000_000 + ts.tv_nsec as i128;
+        let now = nanos(monotonic_timespec(Instant::now()));
+        let later = nanos(monotonic_timespec(Instant::now() + Duration::from_secs(1)));
+        assert!((later - now - 1_000_000_000).abs() < 100_000_000);
+
+        // Past deadlines are clamped to now
+        let past = nanos(monotonic_timespec(Instant::now() - Duration::from_secs(1)));
+        assert!(past >= now);
+    }
+}
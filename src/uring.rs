@@ -1,11 +1,15 @@
 //! Main IoUring struct and setup operations
 
+use crate::builder::{IoUringBuilder, RingMemory};
 use crate::error::{check_ret, Error, Result};
-use crate::flags::SetupFlags;
-use crate::queue::{CompletionQueue, SubmissionQueue};
+use crate::flags::{Features, SetupFlags};
+use crate::queue::{note_sqpoll_wakeup, BackpressurePolicy, CompletionQueue, Cqe, SubmissionQueue};
 use crate::sys;
+use crate::types::{sigmask_ptr, timespec, SigSet};
 use std::mem::MaybeUninit;
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 /// The main io_uring instance
 ///
@@ -22,6 +26,10 @@ use std::os::unix::io::{AsRawFd, RawFd};
 /// ```
 pub struct IoUring {
     ring: sys::io_uring,
+    // Backing memory for NO_MMAP rings; dropped after the ring is torn down
+    _memory: Option<RingMemory>,
+    backpressure: BackpressurePolicy,
+    sqpoll_wakeups: AtomicU64,
 }
 
 impl IoUring {
@@ -64,35 +72,108 @@ impl IoUring {
 
         Ok(Self {
             ring: unsafe { ring.assume_init() },
+            _memory: None,
+            backpressure: BackpressurePolicy::default(),
+            sqpoll_wakeups: AtomicU64::new(0),
         })
     }
 
+    /// Create a builder for configuring every setup option
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use liburing_rs::IoUring;
+    ///
+    /// let ring = IoUring::builder().cq_entries(128).build(32)?;
+    /// # Ok::<(), liburing_rs::Error>(())
+    /// ```
+    pub fn builder() -> IoUringBuilder {
+        IoUringBuilder::new()
+    }
+
     /// Create a new io_uring instance with custom parameters
     ///
     /// This provides the most control over ring configuration.
+    /// Prefer [`IoUring::builder`], which validates the parameters first.
     ///
     /// # Arguments
     ///
     /// * `entries` - Number of submission queue entries
     /// * `params` - Custom io_uring parameters
     pub fn with_params(entries: u32, params: &mut sys::io_uring_params) -> Result<Self> {
+        Self::with_params_and_memory(entries, params, None)
+    }
+
+    pub(crate) fn with_params_and_memory(
+        entries: u32,
+        params: &mut sys::io_uring_params,
+        memory: Option<RingMemory>,
+    ) -> Result<Self> {
         let mut ring = MaybeUninit::<sys::io_uring>::uninit();
 
-        let ret = unsafe { sys::io_uring_queue_init_params(entries, ring.as_mut_ptr(), params) };
+        let ret = match &memory {
+            Some(mem) => unsafe {
+                sys::io_uring_queue_init_mem(
+                    entries,
+                    ring.as_mut_ptr(),
+                    params,
+                    mem.as_ptr() as *mut std::ffi::c_void,
+                    mem.len(),
+                )
+            },
+            None => unsafe { sys::io_uring_queue_init_params(entries, ring.as_mut_ptr(), params) },
+        };
 
         check_ret(ret).map_err(Error::Setup)?;
 
+        let mut ring = unsafe { ring.assume_init() };
+        if params.flags & sys::IORING_SETUP_REGISTERED_FD_ONLY != 0 {
+            // liburing stores the registered index in `ring_fd` as well; it
+            // is no fd, so report none like `close_ring_fd` does
+            ring.ring_fd = -1;
+        }
+
         Ok(Self {
-            ring: unsafe { ring.assume_init() },
+            ring,
+            _memory: memory,
+            backpressure: BackpressurePolicy::default(),
+            sqpoll_wakeups: AtomicU64::new(0),
         })
     }
 
+    /// Enable a ring that was created with `IORING_SETUP_R_DISABLED`
+    pub fn enable_rings(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_enable_rings(&mut self.ring) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
+    }
+
+    /// Get the flags the ring was set up with
+    pub fn setup_flags(&self) -> SetupFlags {
+        SetupFlags::from_bits_retain(self.ring.flags)
+    }
+
+    /// Set what happens when an SQE is requested from a full SQ
+    pub fn set_backpressure(&mut self, policy: BackpressurePolicy) {
+        self.backpressure = policy;
+    }
+
+    /// Get the current backpressure policy
+    pub fn backpressure(&self) -> BackpressurePolicy {
+        self.backpressure
+    }
+
+    /// Get the features the kernel reported at setup time
+    pub fn features(&self) -> Features {
+        Features::from_bits_retain(self.ring.features)
+    }
+
     /// Submit all queued submission queue entries
     ///
-    /// Returns the number of submitted entries.
+    /// Returns the number of submitted entries. See
+    /// [`SubmissionQueue::submit`] for how SQPOLL rings are handled.
     pub fn submit(&mut self) -> Result<usize> {
-        let ret = unsafe { sys::io_uring_submit(&mut self.ring) };
-        check_ret(ret).map(|n| n as usize).map_err(Into::into)
+        self.submission().submit()
     }
 
     /// Submit entries and wait for at least `wait_nr` completions
@@ -101,13 +182,83 @@ impl IoUring {
     ///
     /// * `wait_nr` - Minimum number of completions to wait for
     pub fn submit_and_wait(&mut self, wait_nr: u32) -> Result<usize> {
-        let ret = unsafe { sys::io_uring_submit_and_wait(&mut self.ring, wait_nr) };
-        check_ret(ret).map(|n| n as usize).map_err(Into::into)
+        self.submission().submit_and_wait(wait_nr)
+    }
+
+    /// Submit entries and wait for `wait_nr` completions, up to `timeout`
+    ///
+    /// Returns the first available CQE, or `None` if the timeout expired
+    /// first. While waiting, `sigmask` replaces the thread's signal mask.
+    pub fn submit_and_wait_timeout(
+        &mut self,
+        wait_nr: u32,
+        timeout: Duration,
+        sigmask: Option<&SigSet>,
+    ) -> Result<Option<Cqe<'_>>> {
+        let mut cqe: *mut sys::io_uring_cqe = std::ptr::null_mut();
+        let mut ts = timespec(timeout);
+
+        note_sqpoll_wakeup(&self.ring, &self.sqpoll_wakeups);
+        let ret = unsafe {
+            sys::io_uring_submit_and_wait_timeout(
+                &mut self.ring,
+                &mut cqe,
+                wait_nr,
+                &mut ts,
+                sigmask_ptr(sigmask),
+            )
+        };
+
+        Cqe::from_wait(ret, cqe, &mut self.ring)
+    }
+
+    /// Submit entries and wait with a minimum batching window
+    ///
+    /// Waits up to `min_wait` for `wait_nr` completions; after that, returns
+    /// as soon as any completion is available, or once `timeout` expires.
+    /// Needs [`Features::MIN_TIMEOUT`] (Linux 6.12).
+    pub fn submit_and_wait_min_timeout(
+        &mut self,
+        wait_nr: u32,
+        timeout: Duration,
+        min_wait: Duration,
+        sigmask: Option<&SigSet>,
+    ) -> Result<Option<Cqe<'_>>> {
+        if !self.features().contains(Features::MIN_TIMEOUT) {
+            return Err(Error::NotSupported(
+                "kernel lacks IORING_FEAT_MIN_TIMEOUT".to_string(),
+            ));
+        }
+
+        let mut cqe: *mut sys::io_uring_cqe = std::ptr::null_mut();
+        let mut ts = timespec(timeout);
+        let min_wait_usec = min_wait.as_micros().min(u32::MAX as u128) as u32;
+
+        note_sqpoll_wakeup(&self.ring, &self.sqpoll_wakeups);
+        let ret = unsafe {
+            sys::io_uring_submit_and_wait_min_timeout(
+                &mut self.ring,
+                &mut cqe,
+                wait_nr,
+                &mut ts,
+                min_wait_usec,
+                sigmask_ptr(sigmask),
+            )
+        };
+
+        Cqe::from_wait(ret, cqe, &mut self.ring)
+    }
+
+    /// Flush completions from the kernel's overflow list and run pending
+    /// task work, without submitting or waiting
+    pub fn get_events(&mut self) -> Result<()> {
+        let ret = unsafe { sys::io_uring_get_events(&mut self.ring) };
+        check_ret(ret).map(|_| ()).map_err(Into::into)
     }
 
     /// Get a reference to the submission queue
     pub fn submission(&mut self) -> SubmissionQueue<'_> {
-        SubmissionQueue::new(&mut self.ring)
+        SubmissionQueue::new(&mut self.ring, self.backpressure, &self.sqpoll_wakeups)
     }
 
     /// Get a reference to the completion queue
@@ -115,6 +266,20 @@ impl IoUring {
         CompletionQueue::new(&mut self.ring)
     }
 
+    pub(crate) fn as_raw(&self) -> &sys::io_uring {
+        &self.ring
+    }
+
+    /// Number of times submission had to wake the SQPOLL thread
+    pub(crate) fn sqpoll_wakeups(&self) -> u64 {
+        self.sqpoll_wakeups.load(Ordering::Relaxed)
+    }
+
+    /// Borrow the pieces a [`SubmissionQueue`] is built from
+    pub(crate) fn sq_parts(&mut self) -> (*mut sys::io_uring, BackpressurePolicy, &AtomicU64) {
+        (&mut self.ring, self.backpressure, &self.sqpoll_wakeups)
+    }
+
     /// Get the raw io_uring pointer (for advanced usage)
     ///
     /// # Safety
@@ -1,7 +1,12 @@
 //! Advanced io_uring feature tests
 //! Corresponds to liburing tests: poll.c, timeout.c, link.c, cancel.c
 
-use liburing_rs::{flags::SqeFlags, ops::*, IoUring, Result};
+use liburing_rs::{
+    flags::{CqeFlags, SqeFlags},
+    ops::*,
+    types::SigSet,
+    BackpressurePolicy, Error, IoUring, Result,
+};
 use std::os::unix::io::AsRawFd;
 use std::time::Duration;
 
@@ -94,6 +99,112 @@ fn test_timeout_count() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_wait_cqe_timeout() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    // Nothing in flight, so the wait must time out
+    let start = std::time::Instant::now();
+    assert!(ring
+        .completion()
+        .wait_cqe_timeout(Duration::from_millis(20))?
+        .is_none());
+    assert!(start.elapsed() >= Duration::from_millis(15));
+
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+        sqe.set_user_data(3);
+    }
+    ring.submit()?;
+
+    let mut cq = ring.completion();
+    let cqe = cq
+        .wait_cqe_timeout(Duration::from_secs(1))?
+        .expect("NOP should complete before the timeout");
+    assert_eq!(cqe.user_data(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_wait_cqes_sigmask() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    {
+        let mut sq = ring.submission();
+        for i in 0..2 {
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_user_data(i);
+        }
+    }
+    ring.submit()?;
+
+    let mut mask = SigSet::empty();
+    mask.add(libc::SIGUSR1)?;
+
+    let mut cq = ring.completion();
+    let cqe = cq
+        .wait_cqes(2, Some(Duration::from_secs(1)), Some(&mask))?
+        .expect("both NOPs should complete");
+    assert_eq!(cqe.user_data(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_submit_and_wait_timeout() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    assert!(ring
+        .submit_and_wait_timeout(1, Duration::from_millis(10), None)?
+        .is_none());
+
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+        sqe.set_user_data(9);
+    }
+
+    let cqe = ring
+        .submit_and_wait_timeout(1, Duration::from_secs(1), None)?
+        .expect("NOP should complete before the timeout");
+    assert_eq!(cqe.user_data(), 9);
+
+    Ok(())
+}
+
+#[test]
+fn test_submit_and_wait_min_timeout() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+        sqe.set_user_data(5);
+    }
+
+    // Ask for more completions than will arrive; after the minimum wait
+    // the single NOP is enough
+    let result =
+        ring.submit_and_wait_min_timeout(4, Duration::from_secs(1), Duration::from_millis(5), None);
+
+    match result {
+        Ok(cqe) => {
+            let cqe = cqe.expect("NOP should complete before the timeout");
+            assert_eq!(cqe.user_data(), 5);
+            Ok(())
+        }
+        // Needs Linux 6.12
+        Err(Error::NotSupported(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 #[test]
 fn test_poll_fd() -> Result<()> {
     // Create a pipe
@@ -141,6 +252,71 @@ fn test_poll_fd() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_poll_multishot_flags() -> Result<()> {
+    let mut fds = [0i32; 2];
+    let ret = unsafe { libc::pipe(fds.as_mut_ptr()) };
+    assert_eq!(ret, 0);
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    let mut ring = IoUring::new(8)?;
+
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        unsafe {
+            liburing_rs::sys::io_uring_prep_poll_multishot(sqe, read_fd, libc::POLLIN as u32);
+        }
+        sqe.set_user_data(1);
+    }
+
+    unsafe {
+        libc::write(write_fd, b"x".as_ptr() as *const _, 1);
+    }
+    ring.submit_and_wait(1)?;
+
+    // A multishot poll stays armed, so the first CQE carries F_MORE
+    {
+        let mut cq = ring.completion();
+        let cqe = cq.wait_cqe()?;
+        assert_eq!(cqe.user_data(), 1);
+        assert!(cqe.has_more());
+        assert!(cqe.cqe_flags().contains(CqeFlags::MORE));
+        assert_eq!(cqe.buffer_id(), None);
+        assert!(!cqe.is_notification());
+    }
+
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        unsafe {
+            liburing_rs::sys::io_uring_prep_poll_remove(sqe, 1);
+        }
+        sqe.set_user_data(2);
+    }
+    ring.submit()?;
+
+    // The terminating CQE of the poll no longer has F_MORE
+    let mut seen_final = false;
+    for _ in 0..2 {
+        let mut cq = ring.completion();
+        let cqe = cq.wait_cqe()?;
+        if cqe.user_data() == 1 {
+            assert!(!cqe.has_more());
+            seen_final = true;
+        }
+    }
+    assert!(seen_final);
+
+    unsafe {
+        libc::close(read_fd);
+        libc::close(write_fd);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_linked_operations() -> Result<()> {
     let tmp = tempfile::NamedTempFile::new().unwrap();
@@ -326,6 +502,81 @@ fn test_multiple_rings() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_split_threads() -> Result<()> {
+    const TOTAL: u64 = 256;
+
+    let mut ring = IoUring::builder().cq_entries(TOTAL as u32).build(16)?;
+    let (mut submitter, mut completer) = ring.split()?;
+
+    std::thread::scope(|s| -> Result<()> {
+        let reaper = s.spawn(move || -> Result<u64> {
+            let mut sum = 0;
+            let mut seen = 0;
+            while seen < TOTAL {
+                if let Some(cqe) = completer.wait_cqe_timeout(Duration::from_secs(5))? {
+                    assert!(cqe.is_success());
+                    sum += cqe.user_data();
+                    seen += 1;
+                } else {
+                    panic!("timed out after {} completions", seen);
+                }
+                for completion in completer.drain() {
+                    sum += completion.user_data();
+                    seen += 1;
+                }
+            }
+            Ok(sum)
+        });
+
+        let mut next = 0;
+        while next < TOTAL {
+            while next < TOTAL {
+                let Some(sqe) = submitter.get_sqe() else {
+                    break;
+                };
+                Nop.prepare(sqe);
+                sqe.set_user_data(next);
+                next += 1;
+            }
+            submitter.submit()?;
+        }
+
+        let sum = reaper.join().expect("completion thread panicked")?;
+        assert_eq!(sum, (0..TOTAL).sum::<u64>());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_split_single_issuer() -> Result<()> {
+    let mut ring = match IoUring::builder().single_issuer().build(8) {
+        Ok(ring) => ring,
+        // SINGLE_ISSUER needs Linux 6.0
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    assert!(matches!(ring.split(), Err(Error::InvalidOperation(_))));
+
+    // Submitting stays on this thread; reaping may move
+    let (mut submitter, mut completer) = ring.split_local()?;
+    std::thread::scope(|s| -> Result<()> {
+        let reaper = s.spawn(move || -> Result<Option<u64>> {
+            let cqe = completer.wait_cqe_timeout(Duration::from_secs(5))?;
+            Ok(cqe.map(|cqe| cqe.user_data()))
+        });
+        let sqe = submitter.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+        sqe.set_user_data(7);
+        submitter.submit()?;
+
+        let user_data = reaper.join().expect("completion thread panicked")?;
+        assert_eq!(user_data, Some(7));
+        Ok(())
+    })
+}
+
 #[test]
 fn test_queue_full() -> Result<()> {
     let mut ring = IoUring::new(4)?; // Small ring
@@ -357,3 +608,164 @@ fn test_queue_full() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_backpressure_fail_fast() -> Result<()> {
+    let mut ring = IoUring::new(4)?;
+    assert_eq!(ring.backpressure(), BackpressurePolicy::FailFast);
+
+    let mut sq = ring.submission();
+    for i in 0..4 {
+        let sqe = sq.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+        sqe.set_user_data(i);
+    }
+
+    assert!(matches!(
+        sq.get_sqe_or_err(),
+        Err(Error::SubmissionQueueFull)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_backpressure_flush_and_retry() -> Result<()> {
+    const TOTAL: u64 = 20;
+
+    // 4 SQ slots and 8 CQ slots, so completions also overflow
+    let mut ring = IoUring::builder()
+        .backpressure(BackpressurePolicy::FlushAndRetry)
+        .build(4)?;
+
+    {
+        let mut sq = ring.submission();
+        for i in 0..TOTAL {
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_user_data(i);
+        }
+    }
+    ring.submit()?;
+
+    assert!(ring.stats().has_cq_overflow());
+
+    // Draining moves overflowed CQEs back into the CQ as it frees slots
+    let mut seen: Vec<u64> = ring.completion().drain().map(|c| c.user_data()).collect();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..TOTAL).collect::<Vec<_>>());
+    assert!(!ring.stats().has_cq_overflow());
+
+    Ok(())
+}
+
+#[test]
+fn test_backpressure_keeps_link_chains() -> Result<()> {
+    let mut ring = IoUring::builder()
+        .backpressure(BackpressurePolicy::FlushAndRetry)
+        .build(4)?;
+
+    {
+        let mut sq = ring.submission();
+        for i in 0..4 {
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_user_data(i);
+            sqe.set_flags(SqeFlags::IO_LINK.bits());
+        }
+
+        // Flushing would cut the chain, so the queue stays full
+        assert!(matches!(
+            sq.get_sqe_or_err(),
+            Err(Error::SubmissionQueueFull)
+        ));
+        assert!(sq.is_full());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backpressure_block() -> Result<()> {
+    let mut ring = IoUring::builder()
+        .backpressure(BackpressurePolicy::Block)
+        .build(4)?;
+
+    {
+        let mut sq = ring.submission();
+        for i in 0..6 {
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_user_data(i);
+        }
+    }
+    ring.submit()?;
+
+    assert_eq!(ring.completion().drain().count(), 6);
+    ring.get_events()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_timeout_multishot() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    let timeout = Timeout::multishot(Duration::from_millis(5), 3);
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        timeout.prepare(sqe);
+        sqe.set_user_data(1);
+    }
+    ring.submit()?;
+
+    // Every expiration posts -ETIME; only the last one ends the request
+    for i in 0..3 {
+        let mut cq = ring.completion();
+        let cqe = cq.wait_cqe()?;
+        assert_eq!(cqe.user_data(), 1);
+        assert_eq!(cqe.result(), -libc::ETIME);
+        assert_eq!(cqe.has_more(), i < 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_link_timeout() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+    let mut fds = [0i32; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+    // A read of an empty pipe is cancelled once the linked timeout fires
+    let mut buf = [0u8; 8];
+    let read = unsafe { Read::new(fds[0], buf.as_mut_ptr(), buf.len() as u32, 0) };
+    let timeout = LinkTimeout::new(Duration::from_millis(10));
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        read.prepare(sqe);
+        sqe.set_flags(SqeFlags::IO_LINK.bits());
+        sqe.set_user_data(1);
+
+        let sqe = sq.get_sqe_or_err()?;
+        timeout.prepare(sqe);
+        sqe.set_user_data(2);
+    }
+    ring.submit_and_wait(2)?;
+
+    let mut results = [0; 2];
+    for _ in 0..2 {
+        let mut cq = ring.completion();
+        let cqe = cq.wait_cqe()?;
+        results[cqe.user_data() as usize - 1] = cqe.result();
+    }
+    assert_eq!(results, [-libc::ECANCELED, -libc::ETIME]);
+
+    unsafe {
+        libc::close(fds[0]);
+        libc::close(fds[1]);
+    }
+    Ok(())
+}
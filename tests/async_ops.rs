@@ -1,23 +1,114 @@
 //! Async operation tests
 
+// Shared by the backends' tests, not all of which are always built
+#[cfg(feature = "async")]
+#[allow(dead_code)]
+mod test_ops {
+    use liburing_rs::ops::PrepareOp;
+    use std::sync::Arc;
+
+    /// Writes `len` bytes to `fd`, so the result identifies the op
+    pub struct SizedWrite {
+        pub fd: i32,
+        pub len: u32,
+    }
+
+    impl PrepareOp for SizedWrite {
+        fn prepare(&self, sqe: &mut liburing_rs::sys::io_uring_sqe) {
+            static BUF: [u8; 256] = [0; 256];
+            unsafe {
+                liburing_rs::sys::io_uring_prep_write(
+                    sqe,
+                    self.fd,
+                    BUF.as_ptr() as *const _,
+                    self.len,
+                    0,
+                );
+            }
+        }
+    }
+
+    pub struct PollIn(pub i32);
+
+    impl PrepareOp for PollIn {
+        fn prepare(&self, sqe: &mut liburing_rs::sys::io_uring_sqe) {
+            unsafe {
+                liburing_rs::sys::io_uring_prep_poll_add(sqe, self.0, libc::POLLIN as u32);
+            }
+        }
+    }
+
+    /// Reads into a buffer it owns; `guard` shows when the op is released
+    pub struct OwnedRead {
+        pub fd: i32,
+        pub buf: Box<[u8; 16]>,
+        pub _guard: Arc<()>,
+    }
+
+    impl PrepareOp for OwnedRead {
+        fn prepare(&self, sqe: &mut liburing_rs::sys::io_uring_sqe) {
+            unsafe {
+                liburing_rs::sys::io_uring_prep_read(
+                    sqe,
+                    self.fd,
+                    self.buf.as_ptr() as *mut _,
+                    self.buf.len() as u32,
+                    0,
+                );
+            }
+        }
+    }
+}
+
 #[cfg(feature = "async-tokio")]
 mod tokio_tests {
+    use super::test_ops::{OwnedRead, PollIn, SizedWrite};
     use liburing_rs::async_io::tokio_impl::AsyncIoUring;
     use liburing_rs::ops::{Nop, PrepareOp};
     use liburing_rs::Result;
     use std::os::unix::io::AsRawFd;
+    use std::sync::Arc;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_async_nop() -> Result<()> {
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
         let result = ring.submit_op(Nop).await?;
         assert_eq!(result, 0);
         Ok(())
     }
 
+    #[test]
+    fn test_async_current_thread_moved() -> Result<()> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let ring = rt.block_on(async { AsyncIoUring::new(8) })?;
+
+        // The ring goes on working on whichever thread runs the runtime
+        let result = std::thread::spawn(move || rt.block_on(ring.submit_op(Nop)))
+            .join()
+            .unwrap()?;
+        assert_eq!(result, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_eventfd_nop() -> Result<()> {
+        let ring = AsyncIoUring::with_eventfd(8)?;
+
+        for _ in 0..3 {
+            let result = ring.submit_op(Nop).await?;
+            assert_eq!(result, 0);
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_async_multiple_nops() -> Result<()> {
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         for _ in 0..5 {
             let result = ring.submit_op(Nop).await?;
@@ -27,6 +118,123 @@ mod tokio_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_async_out_of_order_completions() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        let devnull = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .unwrap();
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        // Submitted first, completes last
+        let poll = tokio::spawn(ring.submit_op(PollIn(fds[0])));
+
+        let (a, b, c) = tokio::join!(
+            ring.submit_op(SizedWrite {
+                fd: devnull.as_raw_fd(),
+                len: 1,
+            }),
+            ring.submit_op(SizedWrite {
+                fd: devnull.as_raw_fd(),
+                len: 2,
+            }),
+            ring.submit_op(SizedWrite {
+                fd: devnull.as_raw_fd(),
+                len: 3,
+            }),
+        );
+        assert_eq!((a?, b?, c?), (1, 2, 3));
+        assert!(!poll.is_finished());
+
+        assert_eq!(
+            unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) },
+            1
+        );
+        let revents = poll.await.unwrap()?;
+        assert_ne!(revents & libc::POLLIN as i32, 0);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_async_concurrent_tasks() -> Result<()> {
+        // Far more ops in flight than SQ entries
+        let ring = AsyncIoUring::new(8)?;
+        let devnull = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .unwrap();
+        let fd = devnull.as_raw_fd();
+
+        let tasks: Vec<_> = (0..64u32)
+            .map(|task| {
+                let ring = ring.clone();
+                tokio::spawn(async move {
+                    for i in 0..16u32 {
+                        let len = (task * 16 + i) % 256;
+                        let result = ring.submit_op(SizedWrite { fd, len }).await?;
+                        assert_eq!(result, len as i32);
+                    }
+                    Ok::<_, liburing_rs::Error>(())
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap()?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_cancel_on_drop() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let guard = Arc::new(());
+
+        let read = ring.submit_op(OwnedRead {
+            fd: fds[0],
+            buf: Box::new([0; 16]),
+            _guard: guard.clone(),
+        });
+        assert!(tokio::time::timeout(Duration::from_millis(20), read)
+            .await
+            .is_err());
+
+        // The op is parked until its -ECANCELED CQE arrives
+        for _ in 0..100 {
+            if Arc::strong_count(&guard) == 1 {
+                break;
+            }
+            ring.submit_op(Nop).await?;
+        }
+        assert_eq!(Arc::strong_count(&guard), 1);
+
+        // The cancelled read must not have consumed anything
+        assert_eq!(
+            unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) },
+            1
+        );
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            unsafe { libc::read(fds[0], buf.as_mut_ptr() as *mut _, buf.len()) },
+            1
+        );
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_async_send_recv() -> Result<()> {
         // Create a socket pair
@@ -37,7 +245,7 @@ mod tokio_tests {
 
         let (sock1, sock2) = (fds[0], fds[1]);
 
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         // Send data
         let send_data = b"Hello async io_uring!";
@@ -136,7 +344,7 @@ mod tokio_tests {
         let file = File::open(&file_path).unwrap();
         let fd = file.as_raw_fd();
 
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         // Read data asynchronously
         let mut read_buf = vec![0u8; test_data.len()];
@@ -180,18 +388,347 @@ mod tokio_tests {
 
         Ok(())
     }
+    #[tokio::test]
+    async fn test_async_fs_file() -> Result<()> {
+        use liburing_rs::async_io::fs::File;
+        use std::io::SeekFrom;
+        use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let ring = AsyncIoUring::new(8)?;
+
+        let mut file = File::create(&ring, &path).await?;
+        file.write_all(b"hello world").await?;
+        file.flush().await?;
+        file.sync_all().await?;
+        let metadata = file.metadata().await?;
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 11);
+
+        // Positional writes leave the file position alone
+        let (n, _) = file.write_at(b"HELLO".to_vec(), 0).await?;
+        assert_eq!(n, 5);
+        file.write_all(b"!").await?;
+        file.set_len(8).await?;
+        assert_eq!(file.metadata().await?.len(), 8);
+        drop(file);
+        assert_eq!(std::fs::read(&path).unwrap(), b"HELLO wo");
+
+        let mut file = File::open(&ring, &path).await?;
+        let (n, buf) = file.read_at(Vec::with_capacity(4), 2).await?;
+        assert_eq!((n, &buf[..]), (4, &b"LLO "[..]));
+
+        let mut head = [0u8; 2];
+        file.read_exact(&mut head).await?;
+        assert_eq!(&head, b"HE");
+        assert_eq!(file.seek(SeekFrom::Current(1)).await?, 3);
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).await?;
+        assert_eq!(rest, b"LO wo");
+
+        assert_eq!(file.seek(SeekFrom::Start(6)).await?, 6);
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).await?;
+        assert_eq!(rest, "wo");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_fs_file_outlives_ops() -> Result<()> {
+        use futures::FutureExt;
+        use liburing_rs::async_io::fs::File;
+        use std::os::unix::io::FromRawFd;
+        use tokio::io::AsyncReadExt;
+
+        let ring = AsyncIoUring::new(8)?;
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let pipe = unsafe { std::fs::File::from_raw_fd(fds[0]) };
+        let mut file = File::from_std(&ring, pipe);
+
+        // Queue a read that can't complete, then drop the file
+        let mut byte = [0u8; 1];
+        assert!(file.read(&mut byte).now_or_never().is_none());
+        drop(file);
+
+        // The fd stays open until the cancelled read is reaped
+        assert_ne!(unsafe { libc::fcntl(fds[0], libc::F_GETFD) }, -1);
+        unsafe { libc::close(fds[1]) };
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_multishot() -> Result<()> {
+        use futures::StreamExt;
+        use liburing_rs::ops::{PollMultishot, Timeout};
+
+        let ring = AsyncIoUring::new(8)?;
+
+        // Yields every expiration and ends with the one without F_MORE
+        let ticks: Vec<_> = ring
+            .submit_multishot(Timeout::multishot(Duration::from_millis(2), 3))
+            .collect()
+            .await;
+        assert_eq!(ticks.len(), 3);
+        for (i, tick) in ticks.into_iter().enumerate() {
+            let tick = tick?;
+            assert_eq!(tick.result(), -libc::ETIME);
+            assert_eq!(tick.has_more(), i < 2);
+        }
+
+        // A one-shot op keeps firing once re-armed
+        let rearmed = ring
+            .submit_multishot(Timeout::new(Duration::from_millis(1)))
+            .rearm()
+            .take(3)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(rearmed.len(), 3);
+
+        // ... but not once it fails for good
+        let failed = ring
+            .submit_multishot(PollMultishot::new(-1, libc::POLLIN as u32))
+            .rearm()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].as_ref().unwrap().result(), -libc::EBADF);
+
+        // Dropping the stream cancels the armed poll
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let mut polls = ring.submit_multishot(PollMultishot::new(fds[0], libc::POLLIN as u32));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), polls.next())
+                .await
+                .is_err()
+        );
+        unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) };
+        let ready = polls.next().await.unwrap()?;
+        assert!(ready.has_more());
+        drop(polls);
+        assert_eq!(ring.submit_op(Nop).await?, 0);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_time() -> Result<()> {
+        use futures::StreamExt;
+        use liburing_rs::async_io::time;
+        use std::io::ErrorKind;
+        use std::time::Instant;
+
+        let ring = AsyncIoUring::new(8)?;
+        let start = Instant::now();
+        time::sleep(&ring, Duration::from_millis(10)).await?;
+        assert!(start.elapsed() >= Duration::from_millis(10));
+
+        let sleep = time::sleep_until(&ring, start + Duration::from_millis(20));
+        assert!(!sleep.is_elapsed());
+        sleep.await?;
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // The deadline holds however late the sleep is first polled: timed
+        // from the poll it would last 200ms, rather than about 50ms
+        let start = Instant::now();
+        let sleep = time::sleep(&ring, Duration::from_millis(200));
+        std::thread::sleep(Duration::from_millis(150));
+        let polled = Instant::now();
+        sleep.await?;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(polled.elapsed() < Duration::from_millis(175));
+
+        let mut interval = time::interval(&ring, Duration::from_millis(2));
+        interval.tick().await?;
+        let ticks: Vec<_> = interval.take(2).collect().await;
+        assert_eq!(ticks.len(), 2);
+        assert!(ticks.iter().all(Result::is_ok));
+
+        // An op that never completes is dropped once the timer fires
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let res = time::timeout(
+            &ring,
+            Duration::from_millis(5),
+            ring.submit_op(PollIn(fds[0])),
+        )
+        .await;
+        assert!(matches!(res, Err(liburing_rs::Error::Io(e)) if e.kind() == ErrorKind::TimedOut));
+        assert_eq!(
+            time::timeout(&ring, Duration::from_secs(1), ring.submit_op(Nop)).await??,
+            0
+        );
+
+        // The op it submits is linked to the deadline, so the kernel
+        // cancels it even if the timeout is no longer polled
+        let mut timed = time::timeout(
+            &ring,
+            Duration::from_millis(5),
+            Box::pin(ring.submit_op(PollIn(fds[0]))),
+        );
+        assert!(futures::poll!(&mut timed).is_pending());
+        assert_eq!(timed.into_inner().await?, -libc::ECANCELED);
+
+        // With a linked timeout the kernel cancels it by itself
+        let res = time::timeout_op(&ring, Duration::from_millis(5), PollIn(fds[0])).await;
+        assert!(matches!(res, Err(liburing_rs::Error::Io(e)) if e.kind() == ErrorKind::TimedOut));
+        unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) };
+        let mask = time::timeout_op(&ring, Duration::from_secs(1), PollIn(fds[0])).await?;
+        assert!(mask & libc::POLLIN as i32 != 0);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_tcp_echo() -> Result<()> {
+        use futures::StreamExt;
+        use liburing_rs::async_io::net::{TcpListener, TcpStream};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let ring = AsyncIoUring::new(32)?;
+        let listener = TcpListener::bind(&ring, "127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let server = async {
+            let mut incoming = listener.incoming();
+            for _ in 0..2 {
+                let mut stream = incoming.next().await.unwrap()?;
+                let (mut reader, mut writer) = stream.split();
+                let n = tokio::io::copy(&mut reader, &mut writer).await?;
+                writer.shutdown().await?;
+                assert_eq!(n, 5);
+            }
+            Ok::<_, liburing_rs::Error>(())
+        };
+        let clients = async {
+            for _ in 0..2 {
+                let mut stream = TcpStream::connect(&ring, addr).await?;
+                assert_eq!(stream.peer_addr()?, addr);
+                stream.set_nodelay(true)?;
+                stream.write_all(b"hello").await?;
+                stream.shutdown().await?;
+                let mut echo = Vec::new();
+                stream.read_to_end(&mut echo).await?;
+                assert_eq!(echo, b"hello");
+            }
+            Ok::<_, liburing_rs::Error>(())
+        };
+        let (server, clients) = tokio::join!(server, clients);
+        server?;
+        clients?;
+
+        // A single-shot accept reports the peer's address
+        let (mut client, (accepted, peer)) =
+            tokio::try_join!(TcpStream::connect(&ring, addr), listener.accept())?;
+        assert_eq!(peer, client.local_addr()?);
+        let (mut reader, _writer) = accepted.into_split();
+        client.write_all(b"split").await?;
+        let mut buf = [0u8; 5];
+        reader.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"split");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_incoming_closes_untaken() -> Result<()> {
+        use futures::{FutureExt, StreamExt};
+        use liburing_rs::async_io::net::TcpListener;
+        use std::io::Read;
+
+        let ring = AsyncIoUring::new(8)?;
+        let listener = TcpListener::bind(&ring, "127.0.0.1:0")?;
+
+        // Arm the multishot accept, and let the reactor submit it
+        let mut incoming = listener.incoming();
+        assert!(incoming.next().now_or_never().is_none());
+        tokio::task::yield_now().await;
+
+        // Its CQE is posted before the stream is dropped, but only
+        // dispatched after
+        let mut client = std::net::TcpStream::connect(listener.local_addr()?).unwrap();
+        drop(incoming);
+        ring.submit_op(Nop).await?;
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(client.read(&mut [0u8; 1]).unwrap(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_udp_send_recv() -> Result<()> {
+        use liburing_rs::async_io::net::UdpSocket;
+
+        let ring = AsyncIoUring::new(8)?;
+        let a = UdpSocket::bind(&ring, "127.0.0.1:0")?;
+        let b = UdpSocket::bind(&ring, "127.0.0.1:0")?;
+
+        assert_eq!(a.send_to(b"datagram", b.local_addr()?).await?, 8);
+        let mut buf = [0u8; 16];
+        let (n, from) = b.recv_from(&mut buf).await?;
+        assert_eq!(&buf[..n], b"datagram");
+        assert_eq!(from, a.local_addr()?);
+
+        // Whatever doesn't fit is dropped
+        b.send_to(b"truncated", from).await?;
+        let mut small = [0u8; 4];
+        let (n, _) = a.recv_from(&mut small).await?;
+        assert_eq!((n, &small), (4, b"trun"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_unix_stream() -> Result<()> {
+        use liburing_rs::async_io::net::{UnixListener, UnixStream};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let ring = AsyncIoUring::new(8)?;
+        let (mut a, mut b) = UnixStream::pair(&ring)?;
+        a.write_all(b"ping").await?;
+        let mut buf = [0u8; 4];
+        b.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sock");
+        let listener = UnixListener::bind(&ring, &path)?;
+        let (client, server) =
+            tokio::try_join!(UnixStream::connect(&ring, &path), listener.accept())?;
+        let (_, mut writer) = server.into_split();
+        let (mut reader, _) = client.into_split();
+        writer.write_all(b"pong").await?;
+        writer.shutdown().await?;
+        let mut reply = String::new();
+        reader.read_to_string(&mut reply).await?;
+        assert_eq!(reply, "pong");
+        Ok(())
+    }
 }
 
 #[cfg(feature = "async-async-std")]
 mod async_std_tests {
+    use super::test_ops::{OwnedRead, PollIn, SizedWrite};
     use liburing_rs::async_io::async_std_impl::AsyncIoUring;
     use liburing_rs::ops::{Nop, PrepareOp};
     use liburing_rs::Result;
     use std::os::unix::io::AsRawFd;
+    use std::sync::Arc;
+    use std::time::Duration;
 
     #[async_std::test]
     async fn test_async_nop() -> Result<()> {
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
         let result = ring.submit_op(Nop).await?;
         assert_eq!(result, 0);
         Ok(())
@@ -199,7 +736,7 @@ mod async_std_tests {
 
     #[async_std::test]
     async fn test_async_multiple_nops() -> Result<()> {
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         for _ in 0..5 {
             let result = ring.submit_op(Nop).await?;
@@ -209,6 +746,134 @@ mod async_std_tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn test_async_eventfd_nop() -> Result<()> {
+        let ring = AsyncIoUring::with_eventfd(8)?;
+
+        for _ in 0..3 {
+            let result = ring.submit_op(Nop).await?;
+            assert_eq!(result, 0);
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_async_out_of_order_completions() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        let devnull = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .unwrap();
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        // Submitted first, completes last
+        let poll = async_std::task::spawn(ring.submit_op(PollIn(fds[0])));
+
+        let (a, b, c) = futures::join!(
+            ring.submit_op(SizedWrite {
+                fd: devnull.as_raw_fd(),
+                len: 1,
+            }),
+            ring.submit_op(SizedWrite {
+                fd: devnull.as_raw_fd(),
+                len: 2,
+            }),
+            ring.submit_op(SizedWrite {
+                fd: devnull.as_raw_fd(),
+                len: 3,
+            }),
+        );
+        assert_eq!((a?, b?, c?), (1, 2, 3));
+
+        assert_eq!(
+            unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) },
+            1
+        );
+        let revents = poll.await?;
+        assert_ne!(revents & libc::POLLIN as i32, 0);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_async_concurrent_tasks() -> Result<()> {
+        // Far more ops in flight than SQ entries
+        let ring = AsyncIoUring::new(8)?;
+        let devnull = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .unwrap();
+        let fd = devnull.as_raw_fd();
+
+        let tasks: Vec<_> = (0..64u32)
+            .map(|task| {
+                let ring = ring.clone();
+                async_std::task::spawn(async move {
+                    for i in 0..16u32 {
+                        let len = (task * 16 + i) % 256;
+                        let result = ring.submit_op(SizedWrite { fd, len }).await?;
+                        assert_eq!(result, len as i32);
+                    }
+                    Ok::<_, liburing_rs::Error>(())
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await?;
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_async_cancel_on_drop() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let guard = Arc::new(());
+
+        let read = ring.submit_op(OwnedRead {
+            fd: fds[0],
+            buf: Box::new([0; 16]),
+            _guard: guard.clone(),
+        });
+        assert!(async_std::future::timeout(Duration::from_millis(20), read)
+            .await
+            .is_err());
+
+        // The op is parked until its -ECANCELED CQE arrives
+        for _ in 0..100 {
+            if Arc::strong_count(&guard) == 1 {
+                break;
+            }
+            ring.submit_op(Nop).await?;
+        }
+        assert_eq!(Arc::strong_count(&guard), 1);
+
+        // The cancelled read must not have consumed anything
+        assert_eq!(
+            unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) },
+            1
+        );
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            unsafe { libc::read(fds[0], buf.as_mut_ptr() as *mut _, buf.len()) },
+            1
+        );
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
     #[async_std::test]
     async fn test_async_send_recv() -> Result<()> {
         // Create a socket pair
@@ -219,7 +884,7 @@ mod async_std_tests {
 
         let (sock1, sock2) = (fds[0], fds[1]);
 
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         // Send data
         let send_data = b"Hello async io_uring!";
@@ -318,7 +983,7 @@ mod async_std_tests {
         let file = File::open(&file_path).unwrap();
         let fd = file.as_raw_fd();
 
-        let mut ring = AsyncIoUring::new(8)?;
+        let ring = AsyncIoUring::new(8)?;
 
         // Read data asynchronously
         let mut read_buf = vec![0u8; test_data.len()];
@@ -362,4 +1027,633 @@ mod async_std_tests {
 
         Ok(())
     }
+
+    #[async_std::test]
+    async fn test_async_multishot() -> Result<()> {
+        use futures::StreamExt;
+        use liburing_rs::ops::Timeout;
+
+        let ring = AsyncIoUring::new(8)?;
+        let ticks: Vec<_> = ring
+            .submit_multishot(Timeout::multishot(Duration::from_millis(2), 2))
+            .collect()
+            .await;
+        assert_eq!(ticks.len(), 2);
+        assert!(ticks[0].as_ref().unwrap().has_more());
+        assert!(!ticks[1].as_ref().unwrap().has_more());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_async_fs_file() -> Result<()> {
+        use futures::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+        use liburing_rs::async_io::fs::File;
+        use std::io::SeekFrom;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let ring = AsyncIoUring::new(8)?;
+
+        let mut file = File::create(&ring, &path).await?;
+        file.write_all(b"hello world").await?;
+        file.close().await?;
+        file.sync_data().await?;
+        assert_eq!(file.metadata().await?.len(), 11);
+        drop(file);
+
+        let mut file = File::open(&ring, &path).await?;
+        let mut head = [0u8; 5];
+        file.read_exact(&mut head).await?;
+        assert_eq!(&head, b"hello");
+        assert_eq!(file.seek(SeekFrom::End(-2)).await?, 9);
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).await?;
+        assert_eq!(rest, "ld");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_async_tcp_echo() -> Result<()> {
+        use futures::io::{AsyncReadExt, AsyncWriteExt};
+        use futures::StreamExt;
+        use liburing_rs::async_io::net::{TcpListener, TcpStream};
+
+        let ring = AsyncIoUring::new(32)?;
+        let listener = TcpListener::bind(&ring, "127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let server = async {
+            let mut stream = listener.incoming().next().await.unwrap()?;
+            // `AsyncReadExt::split` would shadow the inherent method
+            let (reader, mut writer) = TcpStream::split(&mut stream);
+            futures::io::copy(reader, &mut writer).await?;
+            writer.close().await?;
+            Ok::<_, liburing_rs::Error>(())
+        };
+        let client = async {
+            let mut stream = TcpStream::connect(&ring, addr).await?;
+            stream.write_all(b"hello").await?;
+            stream.close().await?;
+            let mut echo = Vec::new();
+            stream.read_to_end(&mut echo).await?;
+            assert_eq!(echo, b"hello");
+            Ok::<_, liburing_rs::Error>(())
+        };
+        let (server, client) = futures::join!(server, client);
+        server?;
+        client
+    }
+
+    #[async_std::test]
+    async fn test_async_udp_send_recv() -> Result<()> {
+        use liburing_rs::async_io::net::UdpSocket;
+
+        let ring = AsyncIoUring::new(8)?;
+        let a = UdpSocket::bind(&ring, "127.0.0.1:0")?;
+        let b = UdpSocket::bind(&ring, "127.0.0.1:0")?;
+
+        a.send_to(b"datagram", b.local_addr()?).await?;
+        let mut buf = [0u8; 16];
+        let (n, from) = b.recv_from(&mut buf).await?;
+        assert_eq!(&buf[..n], b"datagram");
+        assert_eq!(from, a.local_addr()?);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+mod generic_tests {
+    use super::test_ops::{OwnedRead, PollIn};
+    use futures::task::noop_waker;
+    use futures::StreamExt;
+    use liburing_rs::async_io::generic_impl::AsyncIoUring;
+    use liburing_rs::ops::{Nop, Timeout};
+    use liburing_rs::Result;
+    use std::future::Future;
+    use std::os::unix::io::AsRawFd;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    /// The loop of a minimal executor: poll, then park in `drive`
+    fn block_on<F: Future>(ring: &AsyncIoUring, fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return out;
+            }
+            ring.drive().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_generic_drive() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        assert_eq!(block_on(&ring, ring.submit_op(Nop))?, 0);
+
+        // Nothing in flight: returns right away
+        ring.drive()?;
+
+        let ticks: Vec<_> = block_on(
+            &ring,
+            ring.submit_multishot(Timeout::multishot(Duration::from_millis(1), 3))
+                .collect(),
+        );
+        assert_eq!(ticks.len(), 3);
+
+        // A dropped op is cancelled and reaped by the next drive
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let guard = Arc::new(());
+        {
+            let read = pin!(ring.submit_op(OwnedRead {
+                fd: fds[0],
+                buf: Box::new([0; 16]),
+                _guard: guard.clone(),
+            }));
+            let waker = noop_waker();
+            assert!(read.poll(&mut Context::from_waker(&waker)).is_pending());
+        }
+        while Arc::strong_count(&guard) > 1 {
+            ring.drive()?;
+        }
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_drive_unlocked() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+
+        // Park one thread in `drive` on an op that takes a while
+        let parked = {
+            let ring = ring.clone();
+            std::thread::spawn(move || {
+                block_on(
+                    &ring,
+                    ring.submit_op(Timeout::new(Duration::from_millis(500))),
+                )
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+
+        // Meanwhile another thread gets its op through
+        let start = std::time::Instant::now();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut nop = pin!(ring.submit_op(Nop));
+        let res = loop {
+            if let Poll::Ready(res) = nop.as_mut().poll(&mut cx) {
+                break res?;
+            }
+            assert!(ring.poll_completions(&mut cx).is_pending());
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(res, 0);
+        assert!(start.elapsed() < Duration::from_millis(250));
+
+        assert_eq!(parked.join().unwrap()?, -libc::ETIME);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_drive_woken() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // An op that stays in flight until the pipe is written to
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let mut poll_in = pin!(ring.submit_op(PollIn(fds[0])));
+        assert!(poll_in.as_mut().poll(&mut cx).is_pending());
+
+        // Block another thread in a single `drive`
+        let block = || {
+            let ring = ring.clone();
+            let (tx, rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move || tx.send(ring.drive()).unwrap());
+            std::thread::sleep(Duration::from_millis(50));
+            rx
+        };
+
+        // This thread reaps the CQE the blocked one was waiting for
+        let mut sleep = pin!(ring.submit_op(Timeout::new(Duration::from_millis(100))));
+        assert!(sleep.as_mut().poll(&mut cx).is_pending());
+        assert!(ring.poll_completions(&mut cx).is_pending());
+        let blocked = block();
+        let res = loop {
+            if let Poll::Ready(res) = sleep.as_mut().poll(&mut cx) {
+                break res?;
+            }
+            assert!(ring.poll_completions(&mut cx).is_pending());
+        };
+        assert_eq!(res, -libc::ETIME);
+        blocked
+            .recv_timeout(Duration::from_secs(5))
+            .expect("drive stayed blocked after its CQE was reaped")?;
+
+        // An op queued meanwhile goes out without this thread driving, and
+        // the blocked thread dispatches it
+        let blocked = block();
+        let mut nop = pin!(ring.submit_op(Nop));
+        assert!(nop.as_mut().poll(&mut cx).is_pending());
+        blocked
+            .recv_timeout(Duration::from_secs(5))
+            .expect("drive stayed blocked after an op was queued")?;
+        assert!(matches!(nop.as_mut().poll(&mut cx), Poll::Ready(Ok(0))));
+
+        unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) };
+        assert!(block_on(&ring, poll_in)? > 0);
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_accept_after_driver_error() -> Result<()> {
+        use liburing_rs::async_io::net::TcpListener;
+
+        let ring = AsyncIoUring::new(8)?;
+        let listener = TcpListener::bind(&ring, "127.0.0.1:0")?;
+        let mut incoming = listener.incoming();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(incoming.poll_next_unpin(&mut cx).is_pending());
+
+        // Point the ring fd at /dev/null so that submitting fails
+        let null = std::fs::File::open("/dev/null").unwrap();
+        assert!(unsafe { libc::dup2(null.as_raw_fd(), ring.as_raw_fd()) } >= 0);
+        assert!(ring.drive().is_err());
+
+        // Every accept from then on fails instead of panicking
+        for _ in 0..2 {
+            match incoming.poll_next_unpin(&mut cx) {
+                Poll::Ready(Some(Err(_))) => {}
+                _ => panic!("accept should fail once the driver stopped"),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_poll_completions() -> Result<()> {
+        let ring = AsyncIoUring::with_eventfd(8)?;
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut poll = pin!(ring.submit_op(PollIn(fds[0])));
+        assert!(poll.as_mut().poll(&mut cx).is_pending());
+        assert!(ring.poll_completions(&mut cx).is_pending());
+        assert!(poll.as_mut().poll(&mut cx).is_pending());
+
+        // The readiness fd signals the completion
+        unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) };
+        let mut pfd = libc::pollfd {
+            fd: ring.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        assert_eq!(unsafe { libc::poll(&mut pfd, 1, 1000) }, 1);
+        assert!(ring.poll_completions(&mut cx).is_pending());
+        match poll.as_mut().poll(&mut cx) {
+            Poll::Ready(res) => assert!(res? & libc::POLLIN as i32 != 0),
+            Poll::Pending => panic!("poll should have completed"),
+        }
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+mod local_tests {
+    use super::test_ops::{OwnedRead, PollIn};
+    use futures::task::noop_waker;
+    use futures::StreamExt;
+    use liburing_rs::async_io::local_impl::AsyncIoUring;
+    use liburing_rs::ops::{Nop, Timeout};
+    use liburing_rs::Result;
+    use std::future::Future;
+    use std::os::unix::io::AsRawFd;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    fn block_on<F: Future>(ring: &AsyncIoUring, fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return out;
+            }
+            ring.drive().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_local_drive() -> Result<()> {
+        let ring = AsyncIoUring::new(8)?;
+        assert_eq!(block_on(&ring, ring.submit_op(Nop))?, 0);
+
+        let ticks: Vec<_> = block_on(
+            &ring,
+            ring.submit_multishot(Timeout::multishot(Duration::from_millis(1), 3))
+                .collect(),
+        );
+        assert_eq!(ticks.len(), 3);
+
+        // A dropped op is cancelled and reaped by the next drive
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let guard = Arc::new(());
+        {
+            let read = pin!(ring.submit_op(OwnedRead {
+                fd: fds[0],
+                buf: Box::new([0; 16]),
+                _guard: guard.clone(),
+            }));
+            let waker = noop_waker();
+            assert!(read.poll(&mut Context::from_waker(&waker)).is_pending());
+        }
+        while Arc::strong_count(&guard) > 1 {
+            ring.drive()?;
+        }
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_poll_completions() -> Result<()> {
+        let ring = AsyncIoUring::with_eventfd(8)?;
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut poll = pin!(ring.submit_op(PollIn(fds[0])));
+        assert!(poll.as_mut().poll(&mut cx).is_pending());
+        assert!(ring.poll_completions(&mut cx).is_pending());
+        assert!(poll.as_mut().poll(&mut cx).is_pending());
+
+        // The poll completes as deferred task work, which the readiness fd
+        // signals and poll_completions runs
+        unsafe { libc::write(fds[1], b"x".as_ptr() as *const _, 1) };
+        let mut pfd = libc::pollfd {
+            fd: ring.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        assert_eq!(unsafe { libc::poll(&mut pfd, 1, 1000) }, 1);
+        assert!(ring.poll_completions(&mut cx).is_pending());
+        match poll.as_mut().poll(&mut cx) {
+            Poll::Ready(res) => assert!(res? & libc::POLLIN as i32 != 0),
+            Poll::Pending => panic!("poll should have completed"),
+        }
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_fs_net_time() -> Result<()> {
+        use futures::io::{AsyncReadExt, AsyncWriteExt};
+        use liburing_rs::async_io::{fs::File, net::UnixStream, time, LocalHandle};
+        use std::time::Instant;
+
+        let ring = AsyncIoUring::new(8)?;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local");
+
+        block_on(&ring, async {
+            let mut file: File<LocalHandle> = File::create(&ring, &path).await?;
+            file.write_all(b"local ring").await?;
+            file.flush().await?;
+
+            let mut contents = String::new();
+            File::open(&ring, &path)
+                .await?
+                .read_to_string(&mut contents)
+                .await?;
+            assert_eq!(contents, "local ring");
+
+            let (mut a, mut b) = UnixStream::pair(&ring)?;
+            a.write_all(b"ping").await?;
+            a.flush().await?;
+            let mut buf = [0u8; 4];
+            b.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+
+            let start = Instant::now();
+            time::sleep(&ring, Duration::from_millis(10)).await?;
+            assert!(start.elapsed() >= Duration::from_millis(10));
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "async-smol")]
+mod smol_tests {
+    use futures::executor::block_on;
+    use futures::future::{select, Either};
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use liburing_rs::async_io::net::UnixStream;
+    use liburing_rs::async_io::smol_impl::AsyncIoUring;
+    use liburing_rs::ops::Nop;
+    use liburing_rs::Result;
+    use std::pin::pin;
+
+    #[test]
+    fn test_smol_reactor() -> Result<()> {
+        block_on(async {
+            let ring = AsyncIoUring::new(8)?;
+            let reactor = pin!(ring.reactor());
+            let test = pin!(async {
+                assert_eq!(ring.submit_op(Nop).await?, 0);
+
+                let (mut a, mut b) = UnixStream::pair(&ring)?;
+                a.write_all(b"smol").await?;
+                let mut buf = [0u8; 4];
+                b.read_exact(&mut buf).await?;
+                assert_eq!(&buf, b"smol");
+                Ok(())
+            });
+            match select(reactor, test).await {
+                Either::Left(_) => panic!("reactor stopped"),
+                Either::Right((res, _)) => res,
+            }
+        })
+    }
+}
+
+#[cfg(feature = "runtime")]
+mod runtime_tests {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use liburing_rs::async_io::net::UnixStream;
+    use liburing_rs::ops::Nop;
+    use liburing_rs::runtime::{self, LocalSet, Runtime};
+    use liburing_rs::Result;
+    use std::cell::Cell;
+    use std::future::{poll_fn, Future};
+    use std::pin::pin;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_runtime_block_on() -> Result<()> {
+        let rt = Runtime::builder().entries(8).build()?;
+        assert_eq!(rt.block_on(rt.ring().submit_op(Nop))?, 0);
+
+        // Ops on sockets opened on the current ring
+        rt.block_on(async {
+            let (mut a, mut b) = UnixStream::pair(&runtime::ring())?;
+            a.write_all(b"ping").await?;
+            let mut buf = [0u8; 4];
+            b.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_runtime_spawn_local() -> Result<()> {
+        let rt = Runtime::new()?;
+        let count = Rc::new(Cell::new(0));
+
+        let total = rt.block_on(async {
+            let tasks: Vec<_> = (1..=4)
+                .map(|i| {
+                    let count = count.clone();
+                    runtime::spawn_local(async move {
+                        runtime::sleep(Duration::from_millis(i)).await;
+                        count.set(count.get() + 1);
+                        i
+                    })
+                })
+                .collect();
+            let mut total = 0;
+            for task in tasks {
+                total += task.await?;
+            }
+            Ok::<_, liburing_rs::Error>(total)
+        })?;
+        assert_eq!(total, 10);
+        assert_eq!(count.get(), 4);
+
+        // Tasks spawned on the runtime carry on in the next block_on
+        let task = rt.spawn_local(async { 7 });
+        assert!(!task.is_finished());
+        assert_eq!(rt.block_on(task)?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_local_set() -> Result<()> {
+        let rt = Runtime::new()?;
+        let local = LocalSet::new();
+        let task = local.spawn_local(async {
+            // Spawns onto the set being run
+            runtime::spawn_local(async { 21 }).await.map(|n| n * 2)
+        });
+        assert_eq!(rt.block_on(local.run_until(task))??, 42);
+        assert!(local.is_empty());
+
+        // Dropping the set drops its unfinished tasks
+        let task = local.spawn_local(std::future::pending::<()>());
+        assert_eq!(local.len(), 1);
+        drop(local);
+        assert!(rt.block_on(task).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_sleep() -> Result<()> {
+        let rt = Runtime::new()?;
+        let start = Instant::now();
+        rt.block_on(runtime::sleep(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let deadline = Instant::now() + Duration::from_millis(10);
+        rt.block_on(runtime::sleep_until(deadline));
+        assert!(Instant::now() >= deadline);
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_idle_parks() -> Result<()> {
+        let rt = Runtime::new()?;
+        let polls = Cell::new(0);
+        rt.block_on(async {
+            let mut sleep = pin!(async {
+                runtime::sleep(Duration::from_millis(1)).await;
+                runtime::sleep(Duration::from_millis(50)).await;
+            });
+            poll_fn(|cx| {
+                polls.set(polls.get() + 1);
+                sleep.as_mut().poll(cx)
+            })
+            .await
+        });
+
+        // Woken by the timeouts' CQEs, not by wakes of its own while parked
+        assert!(polls.get() <= 6, "polled {} times", polls.get());
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_remote_wake() -> Result<()> {
+        let rt = Runtime::new()?;
+        let (tx, rx) = futures::channel::oneshot::channel();
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            tx.send(5).unwrap();
+        });
+
+        // Parked on the ring with nothing of its own in flight
+        assert_eq!(rt.block_on(rx).unwrap(), 5);
+        sender.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_per_core() -> Result<()> {
+        let builder = Runtime::builder().entries(8);
+        let threads = runtime::per_core(builder, |cpu| async move {
+            runtime::ring().submit_op(Nop).await?;
+            let cpus = liburing_rs::types::CpuSet::current()?;
+            assert!(cpus.contains(cpu));
+            assert_eq!(cpus.len(), 1);
+            Ok::<_, liburing_rs::Error>(cpu)
+        })?;
+
+        let cpus: Vec<usize> = liburing_rs::types::CpuSet::current()?.iter().collect();
+        assert_eq!(threads.len(), cpus.len());
+        for (thread, cpu) in threads.into_iter().zip(cpus) {
+            assert_eq!(thread.join().unwrap()??, cpu);
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,5 @@
 //! Basic operation tests
-//! Corresponds to liburing tests: nop.c, fsync.c, close.c
+//! Corresponds to liburing tests: nop.c, fsync.c, close.c, cq-overflow.c
 
 use liburing_rs::{ops::*, IoUring, Result};
 use std::fs::{File, OpenOptions};
@@ -195,6 +195,7 @@ fn test_peek_cqe() -> Result<()> {
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_batch_peek() -> Result<()> {
     let mut ring = IoUring::new(16)?;
     const COUNT: usize = 8;
@@ -220,3 +221,90 @@ fn test_batch_peek() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_completion_iter() -> Result<()> {
+    let mut ring = IoUring::new(16)?;
+    const COUNT: usize = 8;
+
+    {
+        let mut sq = ring.submission();
+        for i in 0..COUNT {
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_user_data(i as u64);
+        }
+    }
+
+    ring.submit_and_wait(COUNT as u32)?;
+
+    // Stop half way; the rest must still be in the queue afterwards
+    let first: Vec<u64> = ring
+        .completion()
+        .iter()
+        .take(COUNT / 2)
+        .map(|c| c.user_data())
+        .collect();
+    assert_eq!(first, (0..COUNT as u64 / 2).collect::<Vec<_>>());
+
+    let rest: Vec<_> = ring.completion().drain().collect();
+    assert_eq!(rest.len(), COUNT / 2);
+    assert!(rest.iter().all(|c| c.is_success()));
+    assert_eq!(rest[0].user_data(), COUNT as u64 / 2);
+
+    assert!(ring.completion().iter().next().is_none());
+    assert!(ring.completion().peek_cqe().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_ring_stats() -> Result<()> {
+    let mut ring = IoUring::new(4)?;
+
+    let stats = ring.stats();
+    assert_eq!(stats.sq_entries, 4);
+    assert_eq!(stats.cq_entries, 8);
+    assert_eq!((stats.sq_ready, stats.cq_ready), (0, 0));
+
+    // Queue SQEs without submitting them
+    {
+        let mut sq = ring.submission();
+        for i in 0..3 {
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_user_data(i);
+        }
+    }
+    assert_eq!(ring.stats().sq_ready, 3);
+
+    ring.submit_and_wait(3)?;
+    let stats = ring.stats();
+    assert_eq!((stats.sq_ready, stats.cq_ready), (0, 3));
+    assert!(!stats.has_cq_overflow());
+
+    // Post more completions than the CQ can hold
+    for _ in 0..2 {
+        {
+            let mut sq = ring.submission();
+            for i in 0..4 {
+                let sqe = sq.get_sqe_or_err()?;
+                Nop.prepare(sqe);
+                sqe.set_user_data(i);
+            }
+        }
+        ring.submit()?;
+    }
+
+    let stats = ring.stats();
+    assert_eq!(stats.cq_ready, 8);
+    assert!(stats.has_cq_overflow());
+    assert_eq!(stats.sq_dropped, 0);
+
+    let info = ring.fdinfo()?;
+    assert_eq!(info.sq_mask(), Some(3));
+    assert_eq!(info.cq_mask(), Some(7));
+    assert_eq!(info.sq_thread(), None);
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 //! Network I/O operation tests
-//! Corresponds to liburing tests: accept.c, connect.c, send.c, recv.c, sendmsg.c, recvmsg.c
+//! Corresponds to liburing tests: accept.c, connect.c, send.c, recv.c, sendmsg.c, recvmsg.c,
+//! napi-test.c
 
-use liburing_rs::{ops::*, IoUring, Result};
+use liburing_rs::{ops::*, Error, IoUring, NapiConfig, NapiTracking, Result};
+use std::io::Write as _;
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::os::unix::io::AsRawFd;
 use std::thread;
@@ -373,3 +375,48 @@ fn test_multiple_send_recv() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_napi_loopback() -> Result<()> {
+    let config = NapiConfig::new(Duration::from_micros(50)).prefer_busy_poll(true);
+    let mut ring = match IoUring::builder().napi(config.clone()).build(8) {
+        Ok(ring) => ring,
+        // NAPI registration needs Linux 6.9
+        Err(Error::Io(e)) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    client.write_all(b"busy").unwrap();
+
+    let mut buf = [0u8; 4];
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        unsafe {
+            Read::new(server.as_raw_fd(), buf.as_mut_ptr(), buf.len() as u32, 0).prepare(sqe);
+        }
+        sqe.set_user_data(1);
+    }
+    ring.submit_and_wait(1)?;
+    {
+        let mut cq = ring.completion();
+        assert_eq!(cq.wait_cqe()?.result(), 4);
+    }
+    assert_eq!(&buf, b"busy");
+
+    // Static tracking needs Linux 6.13, and only polls ids that exist;
+    // loopback has none
+    match ring.register_napi(&config.clone().tracking(NapiTracking::Static)) {
+        Ok(()) => assert!(ring.add_napi_id(1).is_err()),
+        Err(Error::Io(e)) if e.raw_os_error() == Some(libc::EINVAL) => {}
+        Err(e) => return Err(e),
+    }
+
+    // Either registration leaves the same timeout and preference behind
+    let previous = ring.unregister_napi()?;
+    assert_eq!(previous, config);
+    Ok(())
+}
@@ -0,0 +1,399 @@
+//! Ring setup tests
+//! Corresponds to liburing tests: cq-size.c, sq-poll-share.c, ring-leak.c, no-mmap-inval.c,
+//! eventfd.c, eventfd-disable.c, register-restrictions.c
+
+use liburing_rs::{
+    flags::{Features, SetupFlags, SqeFlags},
+    opcode::io_uring_op,
+    ops::*,
+    types::{CpuSet, EventFd, WorkerLimits},
+    Error, IoUring, Restrictions, Result, RingMemory,
+};
+use std::time::Duration;
+
+fn nop_roundtrip(ring: &mut IoUring) -> Result<()> {
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+        sqe.set_user_data(7);
+    }
+
+    ring.submit_and_wait(1)?;
+
+    let mut cq = ring.completion();
+    let cqe = cq.wait_cqe()?;
+    assert_eq!(cqe.user_data(), 7);
+    assert!(cqe.is_success());
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_cq_size() -> Result<()> {
+    let mut ring = IoUring::builder().cq_entries(64).build(8)?;
+    assert!(ring.setup_flags().contains(SetupFlags::CQSIZE));
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_builder_reports_field() {
+    let err = IoUring::builder()
+        .cq_entries(2)
+        .build(8)
+        .err()
+        .expect("undersized CQ should be rejected");
+
+    match err {
+        Error::InvalidParameter { field, .. } => assert_eq!(field, "cq_entries"),
+        other => panic!("unexpected error: {}", other),
+    }
+}
+
+#[test]
+fn test_builder_attach_wq() -> Result<()> {
+    let first = IoUring::new(8)?;
+    let mut second = IoUring::builder().attach_wq(&first).build(8)?;
+    nop_roundtrip(&mut second)
+}
+
+#[test]
+fn test_builder_disabled_then_enabled() -> Result<()> {
+    let mut ring = IoUring::builder().disabled().build(8)?;
+    assert!(ring.setup_flags().contains(SetupFlags::R_DISABLED));
+
+    ring.enable_rings()?;
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_restrictions() -> Result<()> {
+    let mut ring = IoUring::builder().disabled().build(8)?;
+    let restrictions = Restrictions::new()
+        .allow_op(io_uring_op::IORING_OP_NOP)
+        .allow_sqe_flags(SqeFlags::IO_LINK);
+    ring.register_restrictions(&restrictions)?;
+
+    // Restrictions are fixed once registered
+    assert!(ring.register_restrictions(&restrictions).is_err());
+    ring.enable_rings()?;
+
+    nop_roundtrip(&mut ring)?;
+
+    // A rejected SQE ends the submit batch, so send them one at a time
+    for flags in [SqeFlags::empty(), SqeFlags::IO_DRAIN] {
+        {
+            let mut sq = ring.submission();
+            let sqe = sq.get_sqe_or_err()?;
+            if flags.is_empty() {
+                Fsync::new(0).prepare(sqe);
+            } else {
+                Nop.prepare(sqe);
+            }
+            sqe.set_flags(flags.bits());
+            sqe.set_user_data(2);
+        }
+        ring.submit_and_wait(1)?;
+
+        let mut cq = ring.completion();
+        assert_eq!(cq.wait_cqe()?.result(), -libc::EACCES);
+    }
+
+    match ring.register_eventfd(&EventFd::new()?) {
+        Err(Error::Io(e)) => assert_eq!(e.raw_os_error(), Some(libc::EACCES)),
+        other => panic!("expected EACCES, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_restrictions_need_disabled_ring() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+    assert!(matches!(
+        ring.register_restrictions(&Restrictions::new()),
+        Err(Error::InvalidOperation(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_builder_single_issuer_defer_taskrun() -> Result<()> {
+    let mut ring = match IoUring::builder().single_issuer().defer_taskrun().build(8) {
+        Ok(ring) => ring,
+        // DEFER_TASKRUN needs Linux 6.1
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_builder_sqpoll_idle() -> Result<()> {
+    let mut ring = match IoUring::builder()
+        .sqpoll()
+        .sqpoll_idle(Duration::from_millis(10))
+        .build(8)
+    {
+        Ok(ring) => ring,
+        // Unprivileged SQPOLL needs Linux 5.11
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_sqpoll_wakeup_counted() -> Result<()> {
+    let mut ring = match IoUring::builder()
+        .sqpoll()
+        .sqpoll_idle(Duration::from_millis(1))
+        .build(8)
+    {
+        Ok(ring) => ring,
+        // Unprivileged SQPOLL needs Linux 5.11
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    nop_roundtrip(&mut ring)?;
+
+    // Give the poller time to go idle
+    let deadline = std::time::Instant::now() + Duration::from_secs(2);
+    while !ring.stats().needs_wakeup() && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(ring.stats().needs_wakeup(), "SQPOLL thread never went idle");
+
+    // Nothing to submit, so the poller is left asleep
+    let before = ring.stats().sqpoll_wakeups;
+    ring.submit()?;
+    assert_eq!(ring.stats().sqpoll_wakeups, before);
+
+    nop_roundtrip(&mut ring)?;
+    assert!(ring.stats().sqpoll_wakeups > before);
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_no_mmap_no_sqarray() -> Result<()> {
+    let memory = RingMemory::new(64 * 1024)?;
+    let mut ring = match IoUring::builder().no_mmap(memory).no_sqarray().build(8) {
+        Ok(ring) => ring,
+        // NO_MMAP and NO_SQARRAY need Linux 6.5/6.6
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    assert!(ring
+        .setup_flags()
+        .contains(SetupFlags::NO_MMAP | SetupFlags::NO_SQARRAY));
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_builder_registered_fd_only() -> Result<()> {
+    match IoUring::builder().registered_fd_only().build(8) {
+        Err(Error::InvalidParameter { field, .. }) => assert_eq!(field, "flags"),
+        Err(e) => return Err(e),
+        Ok(_) => panic!("REGISTERED_FD_ONLY without NO_MMAP should be rejected"),
+    }
+
+    let memory = RingMemory::new(64 * 1024)?;
+    let mut ring = match IoUring::builder()
+        .no_mmap(memory)
+        .registered_fd_only()
+        .build(8)
+    {
+        Ok(ring) => ring,
+        // REGISTERED_FD_ONLY needs Linux 6.5
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    assert!(ring.setup_flags().contains(SetupFlags::REGISTERED_FD_ONLY));
+    assert_eq!(ring.as_raw_fd(), -1);
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_big_entries_require_flags() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    match ring.submission().get_sqe128() {
+        Err(Error::NotSupported(_)) => {}
+        Err(e) => return Err(e),
+        Ok(_) => panic!("get_sqe128 should fail on a regular ring"),
+    }
+
+    {
+        let mut sq = ring.submission();
+        let sqe = sq.get_sqe_or_err()?;
+        Nop.prepare(sqe);
+    }
+    ring.submit_and_wait(1)?;
+
+    let mut cq = ring.completion();
+    let cqe = cq.wait_cqe()?;
+    assert!(cqe.big_cqe().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_sqe128_cqe32_nop() -> Result<()> {
+    let mut ring = match IoUring::builder().sqe128().cqe32().build(8) {
+        Ok(ring) => ring,
+        // SQE128/CQE32 need Linux 5.19
+        Err(Error::Setup(_)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    {
+        let mut sq = ring.submission();
+        let mut sqe = sq.get_sqe128()?;
+        assert!(sqe.cmd().iter().all(|&b| b == 0));
+
+        Nop.prepare(&mut sqe);
+        sqe.set_user_data(42);
+        sqe.cmd_mut()[79] = 0xff;
+        assert_eq!(sqe.cmd()[79], 0xff);
+    }
+
+    ring.submit_and_wait(1)?;
+
+    let mut cq = ring.completion();
+    let cqe = cq.wait_cqe()?;
+    assert_eq!(cqe.user_data(), 42);
+    assert!(cqe.is_success());
+    assert_eq!(cqe.big_cqe(), Some(&[0, 0]));
+
+    Ok(())
+}
+
+#[test]
+fn test_register_personality() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+    let id = ring.register_personality()?;
+
+    let nop_as = |ring: &mut IoUring, id: u16| -> Result<i32> {
+        {
+            let mut sq = ring.submission();
+            let sqe = sq.get_sqe_or_err()?;
+            Nop.prepare(sqe);
+            sqe.set_personality(id);
+            sqe.set_user_data(1);
+        }
+        ring.submit_and_wait(1)?;
+        let mut cq = ring.completion();
+        let result = cq.wait_cqe()?.result();
+        Ok(result)
+    };
+
+    assert_eq!(nop_as(&mut ring, id)?, 0);
+
+    ring.unregister_personality(id)?;
+    assert_eq!(nop_as(&mut ring, id)?, -libc::EINVAL);
+    assert!(ring.unregister_personality(id).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_register_ring_fd() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    match ring.register_ring_fd() {
+        Ok(()) => {}
+        // Needs Linux 5.18
+        Err(Error::Io(e)) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(()),
+        Err(e) => return Err(e),
+    }
+    nop_roundtrip(&mut ring)?;
+
+    ring.unregister_ring_fd()?;
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_close_ring_fd() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+    if !ring.features().contains(Features::REG_REG_RING) {
+        return Ok(());
+    }
+
+    ring.register_ring_fd()?;
+    ring.close_ring_fd()?;
+    assert_eq!(ring.as_raw_fd(), -1);
+
+    // Submission and waiting keep working through the registered index
+    nop_roundtrip(&mut ring)
+}
+
+#[test]
+fn test_register_eventfd() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+    let efd = EventFd::new()?;
+
+    ring.register_eventfd(&efd)?;
+    assert!(ring.eventfd_enabled());
+    nop_roundtrip(&mut ring)?;
+    assert!(efd.read()? >= 1);
+
+    // Toggled off, completions no longer signal the eventfd
+    ring.set_eventfd_enabled(false)?;
+    assert!(!ring.eventfd_enabled());
+    nop_roundtrip(&mut ring)?;
+    assert!(efd.read().is_err());
+
+    ring.set_eventfd_enabled(true)?;
+    ring.unregister_eventfd()?;
+    nop_roundtrip(&mut ring)?;
+    assert!(efd.read().is_err());
+
+    ring.register_eventfd_async(&efd)?;
+    ring.unregister_eventfd()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_iowq_tuning() -> Result<()> {
+    let mut ring = IoUring::new(8)?;
+
+    let mut cpus = CpuSet::new();
+    cpus.add(0)?;
+    ring.register_iowq_aff(&cpus)?;
+    nop_roundtrip(&mut ring)?;
+    ring.unregister_iowq_aff()?;
+
+    let original = ring.register_iowq_max_workers(WorkerLimits::default())?;
+
+    let limits = WorkerLimits {
+        bounded: 2,
+        unbounded: 4,
+    };
+    ring.register_iowq_max_workers(limits)?;
+    assert_eq!(
+        ring.register_iowq_max_workers(WorkerLimits::default())?,
+        limits
+    );
+
+    // Only touch the unbounded pool
+    let previous = ring.register_iowq_max_workers(WorkerLimits {
+        bounded: 0,
+        unbounded: 8,
+    })?;
+    assert_eq!(previous, limits);
+    assert_eq!(
+        ring.register_iowq_max_workers(WorkerLimits::default())?,
+        WorkerLimits {
+            bounded: 2,
+            unbounded: 8
+        }
+    );
+
+    ring.register_iowq_max_workers(original)?;
+    nop_roundtrip(&mut ring)
+}